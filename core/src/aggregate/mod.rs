@@ -0,0 +1,147 @@
+use crate::report::DeviceReport;
+use crate::smart_device::{DeviceType, SmartDevice};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Min/avg/max over the readings of switched on thermometers.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TemperatureStats {
+    pub min: f64,
+    pub avg: f64,
+    pub max: f64,
+    pub count: usize,
+}
+
+impl TemperatureStats {
+    pub fn from_values(values: impl IntoIterator<Item = f64>) -> Option<Self> {
+        let mut stats: Option<TemperatureStats> = None;
+        let mut sum = 0.0;
+        for value in values {
+            sum += value;
+            stats = Some(match stats {
+                None => TemperatureStats {
+                    min: value,
+                    avg: value,
+                    max: value,
+                    count: 1,
+                },
+                Some(stats) => TemperatureStats {
+                    min: stats.min.min(value),
+                    avg: 0.0,
+                    max: stats.max.max(value),
+                    count: stats.count + 1,
+                },
+            });
+        }
+        stats.map(|stats| TemperatureStats {
+            avg: sum / stats.count as f64,
+            ..stats
+        })
+    }
+}
+
+/// Totals over a set of devices, e.g. a room or the whole house.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Aggregates {
+    pub devices: usize,
+    pub devices_on: usize,
+    /// Current draw of switched on power sockets, in watts.
+    pub power: f64,
+    /// Energy consumed by power sockets, in watt-hours.
+    pub energy: f64,
+    pub temperature: Option<TemperatureStats>,
+}
+
+impl Aggregates {
+    pub fn from_devices<'a>(devices: impl IntoIterator<Item = &'a SmartDevice>) -> Self {
+        Self::from_readings(devices.into_iter().map(|device| {
+            (
+                device.device_type(),
+                device.is_on(),
+                device.get_value(),
+                device.get_energy(),
+            )
+        }))
+    }
+
+    /// Totals over report rows, e.g. the ones left after filtering.
+    pub fn from_reports<'a>(devices: impl IntoIterator<Item = &'a DeviceReport>) -> Self {
+        Self::from_readings(
+            devices
+                .into_iter()
+                .map(|device| (device.kind, device.is_on, device.value, device.energy)),
+        )
+    }
+
+    /// Sums `(type, is on, value, energy)` readings.
+    fn from_readings(readings: impl IntoIterator<Item = (DeviceType, bool, f64, f64)>) -> Self {
+        let mut aggregates = Aggregates::default();
+        let mut temperatures = Vec::new();
+        for (kind, is_on, value, energy) in readings {
+            aggregates.devices += 1;
+            if is_on {
+                aggregates.devices_on += 1;
+            }
+            match kind {
+                DeviceType::PowerSocket => {
+                    if is_on {
+                        aggregates.power += value;
+                    }
+                    aggregates.energy += energy;
+                }
+                DeviceType::Thermometer if is_on => temperatures.push(value),
+                _ => {}
+            }
+        }
+        aggregates.temperature = TemperatureStats::from_values(temperatures);
+        aggregates
+    }
+}
+
+impl fmt::Display for Aggregates {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} of {} devices on, power {:.1} W, energy {:.3} Wh",
+            self.devices_on, self.devices, self.power, self.energy
+        )?;
+        if let Some(t) = self.temperature {
+            write!(
+                f,
+                ", temperature min {:.1} avg {:.1} max {:.1}",
+                t.min, t.avg, t.max
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_temperature_stats() {
+        assert_eq!(TemperatureStats::from_values([]), None);
+
+        let stats = TemperatureStats::from_values([20.0, 23.0, 26.0]).unwrap();
+        assert_eq!(stats.min, 20.0);
+        assert_eq!(stats.avg, 23.0);
+        assert_eq!(stats.max, 26.0);
+        assert_eq!(stats.count, 3);
+    }
+
+    #[test]
+    fn test_aggregates_from_devices() {
+        let mut lamp = SmartDevice::power_socket(60.0);
+        lamp.turn_on();
+        let heater = SmartDevice::power_socket(2000.0);
+        let detector = SmartDevice::smoke_detector();
+
+        let aggregates = Aggregates::from_devices([&lamp, &heater, &detector]);
+        assert_eq!(aggregates.devices, 3);
+        assert_eq!(aggregates.devices_on, 2);
+        assert_eq!(aggregates.power, 60.0);
+        assert_eq!(aggregates.temperature, None);
+    }
+}
@@ -0,0 +1,434 @@
+use crate::error::SmartHouseError;
+use crate::history::History;
+use crate::house::House;
+use crate::report::render::{ReportFormat, csv_field};
+use crate::smart_device::DeviceType;
+use serde::Serialize;
+use std::str::FromStr;
+
+const HOUR: u64 = 3600;
+const DAY: u64 = 24 * HOUR;
+
+/// Price per kWh in `[start_hour, end_hour)` UTC; wraps past midnight when `start_hour > end_hour`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TariffPeriod {
+    pub start_hour: u8,
+    pub end_hour: u8,
+    pub price: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Tariff {
+    Flat(f64),
+    /// Hours not covered by any period cost `default`.
+    TimeOfUse {
+        periods: Vec<TariffPeriod>,
+        default: f64,
+    },
+}
+
+impl Tariff {
+    pub fn price_at(&self, time: u64) -> f64 {
+        match self {
+            Tariff::Flat(price) => *price,
+            Tariff::TimeOfUse { periods, default } => {
+                let hour = (time % DAY / HOUR) as u8;
+                periods
+                    .iter()
+                    .find(|period| match period.start_hour <= period.end_hour {
+                        true => (period.start_hour..period.end_hour).contains(&hour),
+                        false => hour >= period.start_hour || hour < period.end_hour,
+                    })
+                    .map_or(*default, |period| period.price)
+            }
+        }
+    }
+}
+
+/// Parses `0.25` as a flat tariff and `23-7:0.10,0.30` as time-of-use, where the price
+/// without hours is required and applies to the hours no period covers.
+impl FromStr for Tariff {
+    type Err = SmartHouseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || SmartHouseError::InvalidParameter(format!("tariff '{}'", s));
+        let price = |text: &str| {
+            text.trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|price| price.is_finite() && *price >= 0.0)
+                .ok_or_else(invalid)
+        };
+        if !s.contains(':') {
+            return price(s).map(Tariff::Flat);
+        }
+        let mut periods = Vec::new();
+        let mut default = None;
+        for part in s.split(',').map(str::trim) {
+            let Some((hours, period_price)) = part.split_once(':') else {
+                if default.replace(price(part)?).is_some() {
+                    return Err(invalid());
+                }
+                continue;
+            };
+            let (start, end) = hours.split_once('-').ok_or_else(invalid)?;
+            let period = TariffPeriod {
+                start_hour: start.trim().parse().map_err(|_| invalid())?,
+                end_hour: end.trim().parse().map_err(|_| invalid())?,
+                price: price(period_price)?,
+            };
+            if period.start_hour > 24 || period.end_hour > 24 {
+                return Err(invalid());
+            }
+            periods.push(period);
+        }
+        let default = default.ok_or_else(|| {
+            SmartHouseError::InvalidParameter(format!(
+                "tariff '{}': missing the price for hours without a period",
+                s
+            ))
+        })?;
+        Ok(Tariff::TimeOfUse { periods, default })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DeviceEnergy {
+    pub name: String,
+    pub energy_kwh: f64,
+    pub cost: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RoomEnergy {
+    pub name: String,
+    pub devices: Vec<DeviceEnergy>,
+    pub energy_kwh: f64,
+    pub cost: f64,
+}
+
+/// Energy and cost of every power socket over `from..to`, in Unix seconds.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EnergyReport {
+    pub from: u64,
+    pub to: u64,
+    pub rooms: Vec<RoomEnergy>,
+    pub energy_kwh: f64,
+    pub cost: f64,
+}
+
+impl EnergyReport {
+    /// Each recorded sample is taken to hold until the next one, and the latest one until
+    /// the last recording. Fails if samples after `from` were dropped from the history.
+    pub fn compute(
+        house: &House,
+        history: &History,
+        from: u64,
+        to: u64,
+        tariff: &Tariff,
+    ) -> Result<Self, SmartHouseError> {
+        let mut report = EnergyReport {
+            from,
+            to,
+            rooms: Vec::new(),
+            energy_kwh: 0.0,
+            cost: 0.0,
+        };
+        for (room_name, room) in house.rooms() {
+            let mut room_energy = RoomEnergy {
+                name: room_name.to_string(),
+                devices: Vec::new(),
+                energy_kwh: 0.0,
+                cost: 0.0,
+            };
+            for (device_name, device) in room.devices() {
+                if device.device_type() != DeviceType::PowerSocket {
+                    continue;
+                }
+                if !history.covers(room_name, device_name, from) {
+                    return Err(SmartHouseError::InvalidParameter(format!(
+                        "period from {}: history of '{}' in '{}' does not go back that far",
+                        format_date(from),
+                        device_name,
+                        room_name
+                    )));
+                }
+                let (energy_kwh, cost) =
+                    device_energy(history, room_name, device_name, from, to, tariff);
+                room_energy.energy_kwh += energy_kwh;
+                room_energy.cost += cost;
+                room_energy.devices.push(DeviceEnergy {
+                    name: device_name.to_string(),
+                    energy_kwh,
+                    cost,
+                });
+            }
+            report.energy_kwh += room_energy.energy_kwh;
+            report.cost += room_energy.cost;
+            report.rooms.push(room_energy);
+        }
+        Ok(report)
+    }
+
+    /// Supports the text, CSV and JSON formats.
+    pub fn render(&self, format: ReportFormat) -> Result<String, SmartHouseError> {
+        match format {
+            ReportFormat::Text => Ok(self.to_text()),
+            ReportFormat::Csv => Ok(self.to_csv()),
+            ReportFormat::Json => serde_json::to_string_pretty(self)
+                .map_err(|err| SmartHouseError::Storage(err.to_string())),
+            _ => Err(SmartHouseError::Unsupported(format!(
+                "{:?} energy report",
+                format
+            ))),
+        }
+    }
+
+    fn to_text(&self) -> String {
+        let mut text = format!(
+            "Energy report {} .. {}\n{:20}{:20}{:>12}{:>12}\n",
+            format_date(self.from),
+            format_date(self.to),
+            "Room",
+            "Device",
+            "kWh",
+            "Cost"
+        );
+        for room in &self.rooms {
+            for device in &room.devices {
+                text.push_str(&format!(
+                    "{:20}{:20}{:>12.3}{:>12.2}\n",
+                    room.name, device.name, device.energy_kwh, device.cost
+                ));
+            }
+            text.push_str(&format!(
+                "{:20}{:20}{:>12.3}{:>12.2}\n",
+                room.name, "(total)", room.energy_kwh, room.cost
+            ));
+        }
+        text.push_str(&format!(
+            "{:40}{:>12.3}{:>12.2}\n",
+            "House total", self.energy_kwh, self.cost
+        ));
+        text
+    }
+
+    fn to_csv(&self) -> String {
+        let mut csv = String::from("room,device,energy_kwh,cost\n");
+        for room in &self.rooms {
+            for device in &room.devices {
+                csv.push_str(&format!(
+                    "{},{},{},{}\n",
+                    csv_field(&room.name),
+                    csv_field(&device.name),
+                    device.energy_kwh,
+                    device.cost
+                ));
+            }
+        }
+        csv
+    }
+}
+
+fn device_energy(
+    history: &History,
+    room_name: &str,
+    device_name: &str,
+    from: u64,
+    to: u64,
+    tariff: &Tariff,
+) -> (f64, f64) {
+    let watts = |sample: crate::history::Sample| if sample.is_on { sample.value } else { 0.0 };
+    let mut points: Vec<(u64, f64)> = history
+        .at(room_name, device_name, from)
+        .map(|sample| (from, watts(sample)))
+        .into_iter()
+        .collect();
+    points.extend(
+        history
+            .query(room_name, device_name, from, to)
+            .into_iter()
+            .filter(|sample| sample.time > from && sample.time < to)
+            .map(|sample| (sample.time, watts(sample))),
+    );
+
+    let last = history.last_recorded().map_or(to, |time| time.min(to));
+    let mut energy_kwh = 0.0;
+    let mut cost = 0.0;
+    for (i, &(start, power)) in points.iter().enumerate() {
+        let end = points.get(i + 1).map_or(last, |&(time, _)| time);
+        let mut time = start;
+        while time < end {
+            let next = match tariff {
+                Tariff::Flat(_) => end,
+                Tariff::TimeOfUse { .. } => end.min((time / HOUR + 1) * HOUR),
+            };
+            let kwh = power * (next - time) as f64 / HOUR as f64 / 1000.0;
+            energy_kwh += kwh;
+            cost += kwh * tariff.price_at(time);
+            time = next;
+        }
+    }
+    (energy_kwh, cost)
+}
+
+/// Parses a `YYYY-MM-DD` date into Unix seconds at UTC midnight.
+pub fn parse_date(date: &str) -> Result<u64, SmartHouseError> {
+    let invalid = || SmartHouseError::InvalidParameter(format!("date '{}'", date));
+    let mut parts = date.trim().splitn(3, '-');
+    let mut next = || parts.next().and_then(|part| part.parse::<i64>().ok());
+    let (year, month, day) = match (next(), next(), next()) {
+        (Some(y), Some(m), Some(d))
+            if (1..=12).contains(&m) && (1..=days_in_month(y, m)).contains(&d) =>
+        {
+            (y, m, d)
+        }
+        _ => return Err(invalid()),
+    };
+    // Days from civil, see https://howardhinnant.github.io/date_algorithms.html
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146097 + doe - 719468;
+    u64::try_from(days)
+        .map(|days| days * DAY)
+        .map_err(|_| invalid())
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+fn format_date(time: u64) -> String {
+    let days = (time / DAY) as i64 + 719468;
+    let era = days.div_euclid(146097);
+    let doe = days - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + (month <= 2) as i64;
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Formats a Unix time as `YYYY-MM-DD HH:MM UTC`.
+pub fn format_time(time: u64) -> String {
+    format!(
+        "{} {:02}:{:02} UTC",
+        format_date(time),
+        time % DAY / HOUR,
+        time % HOUR / 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::house;
+
+    /// A hall with a heater that is on from 01:00 to 03:00 of the day starting at `day`.
+    fn heated_hall(day: u64) -> (House, History) {
+        let mut house = house! {
+            "Hall" {
+                "Heater": power_socket(2000.0),
+                "Detector": smoke_detector(),
+            }
+        };
+        let mut history = History::new(100, DAY);
+        history.record(&house, day).unwrap();
+        for (time, on) in [(day + HOUR, true), (day + 3 * HOUR, false)] {
+            let hall = house.get_mut_room("Hall").unwrap();
+            hall.get_mut_device("Heater").unwrap().switch(on).unwrap();
+            history.record(&house, time).unwrap();
+        }
+        (house, history)
+    }
+
+    #[test]
+    fn test_flat_tariff() {
+        let day = parse_date("2026-10-01").unwrap();
+        let (house, history) = heated_hall(day);
+
+        let report =
+            EnergyReport::compute(&house, &history, day, day + DAY, &Tariff::Flat(0.5)).unwrap();
+        assert_eq!(report.rooms[0].devices.len(), 1);
+        assert_eq!(report.energy_kwh, 4.0);
+        assert_eq!(report.cost, 2.0);
+
+        let partial = EnergyReport::compute(
+            &house,
+            &history,
+            day + 2 * HOUR,
+            day + DAY,
+            &Tariff::Flat(0.5),
+        )
+        .unwrap();
+        assert_eq!(partial.energy_kwh, 2.0);
+    }
+
+    #[test]
+    fn test_time_of_use_tariff() {
+        let day = parse_date("2026-10-01").unwrap();
+        let (house, history) = heated_hall(day);
+
+        let tariff: Tariff = "23-2:0.1,0.3".parse().unwrap();
+        assert_eq!(tariff.price_at(day + 23 * HOUR), 0.1);
+        assert_eq!(tariff.price_at(day + 12 * HOUR), 0.3);
+        for invalid in [
+            "-0.1",
+            "NaN",
+            "23-2:0.1",
+            "23-2:-0.1,0.3",
+            "23-2:0.1,0.3,0.2",
+        ] {
+            assert!(invalid.parse::<Tariff>().is_err(), "{}", invalid);
+        }
+
+        let report = EnergyReport::compute(&house, &history, day, day + DAY, &tariff).unwrap();
+        assert!((report.cost - (2.0 * 0.1 + 2.0 * 0.3)).abs() < 1e-9);
+
+        let csv = report.render(ReportFormat::Csv).unwrap();
+        assert!(csv.starts_with("room,device,energy_kwh,cost\nHall,Heater,4,"));
+        assert!(report.render(ReportFormat::Html).is_err());
+    }
+
+    #[test]
+    fn test_period_bounded_by_history() {
+        let mut house = house! { "Hall" { "Heater": power_socket(2000.0) } };
+        let day = parse_date("2026-10-01").unwrap();
+        let mut history = History::new(2, DAY);
+        history.record(&house, day).unwrap();
+        let hall = house.get_mut_room("Hall").unwrap();
+        hall.get_mut_device("Heater").unwrap().turn_on();
+        history.record(&house, day + HOUR).unwrap();
+        let hall = house.get_mut_room("Hall").unwrap();
+        hall.get_mut_device("Heater").unwrap().set_value(1000.0);
+        history.record(&house, day + 2 * HOUR).unwrap();
+        history.record(&house, day + 3 * HOUR).unwrap();
+
+        let flat = Tariff::Flat(1.0);
+        let report = EnergyReport::compute(&house, &history, day + HOUR, day + DAY, &flat);
+        assert_eq!(report.unwrap().energy_kwh, 3.0);
+        assert!(EnergyReport::compute(&house, &history, day, day + DAY, &flat).is_err());
+    }
+
+    #[test]
+    fn test_dates() {
+        assert_eq!(parse_date("1970-01-02"), Ok(DAY));
+        assert_eq!(format_date(parse_date("2024-02-29").unwrap()), "2024-02-29");
+        assert!(parse_date("2026-13-01").is_err());
+        assert!(parse_date("2026-02-31").is_err());
+        assert!(parse_date("2026-02-29").is_err());
+        assert!(parse_date("2026-04-31").is_err());
+        assert!(parse_date("2000-02-29").is_ok());
+    }
+}
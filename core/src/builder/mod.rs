@@ -1,15 +1,19 @@
+use crate::error::SmartHouseError;
 use crate::house::House;
 use crate::room::Room;
-use crate::smart_device::SmartDevice;
+use crate::smart_device::{DeviceType, SmartDevice};
 
 #[derive(Default)]
 pub struct HouseBuilder {
     pub house: House,
+    pub errors: Vec<SmartHouseError>,
 }
 
 pub struct RoomBuilder<'a> {
     pub house: House,
     pub room_name: &'a str,
+    pub room: Room,
+    pub errors: Vec<SmartHouseError>,
 }
 
 impl HouseBuilder {
@@ -21,29 +25,170 @@ impl HouseBuilder {
         RoomBuilder {
             house: self.house,
             room_name: name,
+            room: Room::new(),
+            errors: self.errors,
         }
     }
 
-    pub fn build(self) -> House {
-        self.house
+    pub fn add_alarm_shutoff(mut self, room_name: &str, device_name: &str) -> Self {
+        if let Err(err) = self.house.add_alarm_shutoff(room_name, device_name) {
+            self.errors.push(err);
+        }
+        self
+    }
+
+    /// Returns the house, or every problem met while building it.
+    pub fn try_build(self) -> Result<House, Vec<SmartHouseError>> {
+        match self.errors.is_empty() {
+            true => Ok(self.house),
+            false => Err(self.errors),
+        }
+    }
+
+    /// Like [`HouseBuilder::try_build`], but reports only the first problem.
+    pub fn build(self) -> Result<House, SmartHouseError> {
+        self.try_build().map_err(|mut errors| errors.remove(0))
     }
 }
 
 impl RoomBuilder<'_> {
-    pub fn add_device(mut self, name: &str, device: SmartDevice) -> Self {
-        let room = self.house.get_mut_room(self.room_name);
-        if room.is_none() {
-            self.house
-                .add_room(self.room_name, Option::from(Room::new()));
+    pub fn add_device(self, name: &str, device: SmartDevice) -> Self {
+        self.try_add_device(name, Ok(device))
+    }
+
+    /// Accepts the result of a fallible constructor such as [`SmartDevice::try_thermometer`],
+    /// so that backend failures are reported by the builder instead of panicking.
+    pub fn try_add_device(
+        mut self,
+        name: &str,
+        device: Result<SmartDevice, SmartHouseError>,
+    ) -> Self {
+        let result = match name.is_empty() {
+            true => Err(SmartHouseError::EmptyName(format!(
+                "device in room '{}'",
+                self.room_name
+            ))),
+            false => device
+                .and_then(|device| check_parameter(name, &device).map(|_| device))
+                .and_then(|device| self.room.add_device(name, device)),
+        };
+        if let Err(err) = result {
+            self.errors.push(err);
         }
-        self.house
-            .get_mut_room(self.room_name)
-            .unwrap()
-            .add_device(name, device);
         self
     }
 
-    pub fn build(self) -> HouseBuilder {
-        HouseBuilder { house: self.house }
+    pub fn add_tag(mut self, tag: &str) -> Self {
+        self.room.labels_mut().add_tag(tag);
+        self
+    }
+
+    pub fn set_meta(mut self, key: &str, value: &str) -> Self {
+        self.room.labels_mut().set_meta(key, value);
+        self
+    }
+
+    pub fn build(mut self) -> HouseBuilder {
+        let result = match self.room_name.is_empty() {
+            true => Err(SmartHouseError::EmptyName("room".to_string())),
+            false => self.house.add_room(self.room_name, Some(self.room)),
+        };
+        if let Err(err) = result {
+            self.errors.push(err);
+        }
+        HouseBuilder {
+            house: self.house,
+            errors: self.errors,
+        }
+    }
+}
+
+/// Rejects device parameters the builder would not accept, e.g. a negative wattage.
+pub fn check_parameter(name: &str, device: &SmartDevice) -> Result<(), SmartHouseError> {
+    let value = device.get_parameter();
+    if !value.is_finite() {
+        return Err(SmartHouseError::InvalidParameter(format!(
+            "{}: value {} is not a finite number",
+            name, value
+        )));
+    }
+    if device.device_type() == DeviceType::PowerSocket && value < 0.0 {
+        return Err(SmartHouseError::InvalidParameter(format!(
+            "{}: power must not be negative",
+            name
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_empty_room() {
+        let house = HouseBuilder::new()
+            .add_room("Hall")
+            .build()
+            .build()
+            .unwrap();
+        assert!(house.get_room("Hall").is_some());
+    }
+
+    #[test]
+    fn test_build_duplicate_device() {
+        let result = HouseBuilder::new()
+            .add_room("Hall")
+            .add_device("Socket", SmartDevice::power_socket(10.0))
+            .add_device("Socket", SmartDevice::power_socket(20.0))
+            .build()
+            .build();
+        assert_eq!(
+            result.err(),
+            Some(SmartHouseError::DuplicateName("Socket".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_try_build_collects_all_errors() {
+        let errors = HouseBuilder::new()
+            .add_room("Hall")
+            .add_device("", SmartDevice::power_socket(10.0))
+            .add_device("Heater", SmartDevice::power_socket(-5.0))
+            .try_add_device(
+                "Thermometer",
+                Err(SmartHouseError::Backend("no library".to_string())),
+            )
+            .build()
+            .add_room("Hall")
+            .build()
+            .add_alarm_shutoff("Hall", "Oven")
+            .try_build()
+            .err()
+            .unwrap();
+        assert_eq!(
+            errors,
+            vec![
+                SmartHouseError::EmptyName("device in room 'Hall'".to_string()),
+                SmartHouseError::InvalidParameter("Heater: power must not be negative".to_string()),
+                SmartHouseError::Backend("no library".to_string()),
+                SmartHouseError::DuplicateName("Hall".to_string()),
+                SmartHouseError::DeviceNotFound("Oven".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_duplicate_room_is_not_merged() {
+        let mut builder = HouseBuilder::new()
+            .add_room("Hall")
+            .add_device("Lamp", SmartDevice::power_socket(60.0))
+            .build()
+            .add_room("Hall")
+            .add_device("Heater", SmartDevice::power_socket(2000.0))
+            .build();
+        assert_eq!(builder.errors.len(), 1);
+        let hall = builder.house.get_mut_room("Hall").unwrap();
+        assert_eq!(hall.get_devices_names(), vec!["Lamp".to_string()]);
     }
 }
@@ -0,0 +1,475 @@
+use super::DEFAULT_HOUSE_FILE;
+use clap::{Args, Subcommand};
+use serde::Serialize;
+use smart_house_lib::builder::check_parameter;
+use smart_house_lib::config::DeviceConfig;
+use smart_house_lib::error::SmartHouseError;
+use smart_house_lib::house::House;
+use smart_house_lib::report::render::ReportFormat;
+use smart_house_lib::report::{DeviceReport, HouseReport, ReportOptions};
+use smart_house_lib::room::Room;
+use smart_house_lib::smart_device::{DeviceType, SmartDevice};
+use std::path::PathBuf;
+
+/// Exit code for a failed operation, e.g. an unknown room.
+pub const EXIT_FAILED: u8 = 1;
+/// Exit code for a house file that cannot be read or written.
+pub const EXIT_STORAGE: u8 = 3;
+/// Exit code for a device whose backend library cannot be loaded.
+pub const EXIT_BACKEND: u8 = 4;
+
+#[derive(Args)]
+pub struct FileArgs {
+    /// Saved house file
+    #[arg(short, long, global = true, default_value = DEFAULT_HOUSE_FILE)]
+    pub file: PathBuf,
+    /// Print results as JSON
+    #[arg(long, global = true)]
+    pub json: bool,
+}
+
+#[derive(Subcommand)]
+pub enum HouseCommand {
+    /// Add, remove or list rooms
+    Room {
+        #[command(flatten)]
+        file: FileArgs,
+        #[command(subcommand)]
+        command: RoomCommand,
+    },
+    /// Add, remove, switch or read devices
+    Device {
+        #[command(flatten)]
+        file: FileArgs,
+        #[command(subcommand)]
+        command: DeviceCommand,
+    },
+    /// Print a report of the whole house
+    Report {
+        #[command(flatten)]
+        file: FileArgs,
+        /// text, json, csv, md or html
+        #[arg(long, default_value = "text")]
+        format: ReportFormat,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum RoomCommand {
+    /// Add an empty room, creating the house file if it does not exist
+    Add { name: String },
+    /// Remove a room with all its devices
+    Rm { name: String },
+    /// List rooms
+    Ls,
+}
+
+#[derive(Subcommand)]
+pub enum DeviceCommand {
+    /// Add a device, switched off
+    Add {
+        room: String,
+        name: String,
+        /// PowerSocket, Thermometer, DoorLock, Blind or SmokeDetector
+        #[arg(short = 't', long = "type")]
+        kind: DeviceType,
+        /// Wattage, temperature or blind position
+        #[arg(long, default_value_t = 0.0, allow_negative_numbers = true)]
+        value: f64,
+    },
+    /// Remove a device
+    Rm { room: String, name: String },
+    /// Switch a device on, or lock a door
+    On { room: String, name: String },
+    /// Switch a device off
+    Off { room: String, name: String },
+    /// Set the wattage, temperature or blind position
+    Set {
+        room: String,
+        name: String,
+        #[arg(allow_negative_numbers = true)]
+        value: f64,
+    },
+    /// Print type, state and value separated by tabs
+    Get { room: String, name: String },
+}
+
+#[derive(Serialize)]
+struct RoomListing<'a> {
+    name: &'a str,
+    devices: Vec<&'a str>,
+}
+
+impl HouseCommand {
+    fn file(&self) -> &FileArgs {
+        match self {
+            HouseCommand::Room { file, .. }
+            | HouseCommand::Device { file, .. }
+            | HouseCommand::Report { file, .. } => file,
+        }
+    }
+
+    fn is_read_only(&self) -> bool {
+        matches!(
+            self,
+            HouseCommand::Room {
+                command: RoomCommand::Ls,
+                ..
+            } | HouseCommand::Device {
+                command: DeviceCommand::Get { .. },
+                ..
+            } | HouseCommand::Report { .. }
+        )
+    }
+}
+
+/// Loads the house file, runs `command` on it and saves the house back if it changed.
+/// Returns what should be printed on success.
+pub fn run(command: &HouseCommand) -> Result<String, SmartHouseError> {
+    let file = command.file();
+    let creates = matches!(
+        command,
+        HouseCommand::Room {
+            command: RoomCommand::Add { .. },
+            ..
+        }
+    );
+    let mut house = match creates && !file.file.exists() {
+        true => House::new(),
+        false => {
+            let (house, warnings) = House::load_with_warnings(&file.file)?;
+            for warning in warnings {
+                eprintln!("Warning: {}", warning);
+            }
+            house
+        }
+    };
+    let output = execute(&mut house, command)?;
+    if !command.is_read_only() {
+        house.save(&file.file)?;
+    }
+    Ok(output)
+}
+
+pub fn exit_code(err: &SmartHouseError) -> u8 {
+    match err {
+        SmartHouseError::Storage(_) | SmartHouseError::Config { .. } => EXIT_STORAGE,
+        SmartHouseError::Backend(_) => EXIT_BACKEND,
+        _ => EXIT_FAILED,
+    }
+}
+
+fn execute(house: &mut House, command: &HouseCommand) -> Result<String, SmartHouseError> {
+    let json = command.file().json;
+    match command {
+        HouseCommand::Room { command, .. } => execute_room(house, command, json),
+        HouseCommand::Device { command, .. } => execute_device(house, command, json),
+        HouseCommand::Report { format, .. } => Ok(terminated(
+            format
+                .renderer()
+                .render(&HouseReport::from_house(house), &ReportOptions::default()),
+        )),
+    }
+}
+
+fn execute_room(
+    house: &mut House,
+    command: &RoomCommand,
+    json: bool,
+) -> Result<String, SmartHouseError> {
+    match command {
+        RoomCommand::Add { name } if name.trim().is_empty() => {
+            Err(SmartHouseError::EmptyName("room".to_string()))
+        }
+        RoomCommand::Add { name } => house.add_room(name, None).map(|_| String::new()),
+        RoomCommand::Rm { name } => house.del_room(name).map(|_| String::new()),
+        RoomCommand::Ls if json => {
+            let rooms: Vec<RoomListing> = house
+                .rooms()
+                .map(|(name, room)| RoomListing {
+                    name,
+                    devices: room.devices().map(|(device, _)| device).collect(),
+                })
+                .collect();
+            json_output(&rooms)
+        }
+        RoomCommand::Ls => Ok(house
+            .rooms()
+            .map(|(name, _)| format!("{}\n", name))
+            .collect()),
+    }
+}
+
+fn execute_device(
+    house: &mut House,
+    command: &DeviceCommand,
+    json: bool,
+) -> Result<String, SmartHouseError> {
+    match command {
+        DeviceCommand::Add {
+            room,
+            name,
+            kind,
+            value,
+        } => {
+            if name.trim().is_empty() {
+                return Err(SmartHouseError::EmptyName(format!(
+                    "device in room '{}'",
+                    room
+                )));
+            }
+            let device = create_device(name, *kind, *value)?;
+            room_mut(house, room)?.add_device(name, device)?;
+        }
+        DeviceCommand::Rm { room, name } => {
+            house.del_device(room, name)?;
+        }
+        DeviceCommand::On { room, name } => {
+            let device = device_mut(house, room, name)?;
+            device.switch(true)?;
+            settle(device);
+        }
+        DeviceCommand::Off { room, name } => {
+            let device = device_mut(house, room, name)?;
+            device.switch(false)?;
+            settle(device);
+        }
+        DeviceCommand::Set { room, name, value } => {
+            let device = device_mut(house, room, name)?;
+            if !device.set_value(*value) {
+                return Err(SmartHouseError::Unsupported(format!(
+                    "{:?} does not accept value {}",
+                    device.device_type(),
+                    value
+                )));
+            }
+            settle(device);
+        }
+        DeviceCommand::Get { room, name } => {
+            let device = house.get_device(room, name)?;
+            return match json {
+                true => json_output(&DeviceReport::from_device(name, device)),
+                false => Ok(format!(
+                    "{:?}\t{}\t{}\n",
+                    device.device_type(),
+                    device.get_state(),
+                    device.get_value()
+                )),
+            };
+        }
+    }
+    Ok(String::new())
+}
+
+/// No simulated time passes between one-shot commands, so blinds move at once.
+fn settle(device: &mut SmartDevice) {
+    if device.is_blind() {
+        device.settle();
+    }
+}
+
+fn json_output(value: &impl Serialize) -> Result<String, SmartHouseError> {
+    serde_json::to_string_pretty(value)
+        .map(terminated)
+        .map_err(|err| SmartHouseError::Storage(err.to_string()))
+}
+
+/// Ends `text` with a newline, which JSON renderers leave out.
+pub(super) fn terminated(mut text: String) -> String {
+    if !text.ends_with('\n') {
+        text.push('\n');
+    }
+    text
+}
+
+/// A switched off device of `kind`, like one loaded from a saved house.
+pub(super) fn create_device(
+    name: &str,
+    kind: DeviceType,
+    value: f64,
+) -> Result<SmartDevice, SmartHouseError> {
+    let device = DeviceConfig {
+        name: name.to_string(),
+        kind,
+        value,
+        on: false,
+        codes: Vec::new(),
+        target: None,
+        speed: None,
+        jammed: false,
+        audit: Vec::new(),
+        tags: Vec::new(),
+        metadata: Default::default(),
+    }
+    .try_create_device()?;
+    check_parameter(name, &device)?;
+    Ok(device)
+}
+
+pub(super) fn room_mut<'a>(
+    house: &'a mut House,
+    room: &str,
+) -> Result<&'a mut Room, SmartHouseError> {
+    house
+        .get_mut_room(room)
+        .ok_or_else(|| SmartHouseError::RoomNotFound(room.to_string()))
+}
+
+pub(super) fn device_mut<'a>(
+    house: &'a mut House,
+    room: &str,
+    name: &str,
+) -> Result<&'a mut SmartDevice, SmartHouseError> {
+    room_mut(house, room)?
+        .get_mut_device(name)
+        .ok_or_else(|| SmartHouseError::DeviceNotFound(name.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, fs};
+
+    fn file(json: bool) -> FileArgs {
+        FileArgs {
+            file: env::temp_dir().join(format!("smart_house_cli_{}.json", std::process::id())),
+            json,
+        }
+    }
+
+    fn room(command: RoomCommand) -> HouseCommand {
+        HouseCommand::Room {
+            file: file(false),
+            command,
+        }
+    }
+
+    fn lamp(command: fn(String, String) -> DeviceCommand) -> HouseCommand {
+        HouseCommand::Device {
+            file: file(false),
+            command: command("Hall".to_string(), "Lamp".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_room_and_device_commands() {
+        let mut house = House::new();
+        let add_room = room(RoomCommand::Add {
+            name: "Hall".to_string(),
+        });
+        execute(&mut house, &add_room).unwrap();
+        let add = lamp(|room, name| DeviceCommand::Add {
+            room,
+            name,
+            kind: DeviceType::PowerSocket,
+            value: 60.0,
+        });
+        execute(&mut house, &add).unwrap();
+        assert_eq!(
+            execute(&mut house, &add),
+            Err(SmartHouseError::DuplicateName("Lamp".to_string()))
+        );
+        let negative = lamp(|room, _| DeviceCommand::Add {
+            room,
+            name: "Heater".to_string(),
+            kind: DeviceType::PowerSocket,
+            value: -5.0,
+        });
+        let err = execute(&mut house, &negative).unwrap_err();
+        assert!(matches!(err, SmartHouseError::InvalidParameter(_)));
+        assert_eq!(exit_code(&err), EXIT_FAILED);
+
+        execute(
+            &mut house,
+            &lamp(|room, name| DeviceCommand::On { room, name }),
+        )
+        .unwrap();
+        let get = lamp(|room, name| DeviceCommand::Get { room, name });
+        assert_eq!(execute(&mut house, &get).unwrap(), "PowerSocket\tON\t60\n");
+
+        let ls = HouseCommand::Room {
+            file: file(true),
+            command: RoomCommand::Ls,
+        };
+        let listing: serde_json::Value =
+            serde_json::from_str(&execute(&mut house, &ls).unwrap()).unwrap();
+        assert_eq!(listing[0]["devices"][0], "Lamp");
+    }
+
+    #[test]
+    fn test_set_moves_blind_at_once() {
+        let mut house = smart_house_lib::house! { "Hall" { "Blind": blind(0.0) } };
+        let blind = |command: fn(String, String) -> DeviceCommand| HouseCommand::Device {
+            file: file(false),
+            command: command("Hall".to_string(), "Blind".to_string()),
+        };
+        let set = blind(|room, name| DeviceCommand::Set {
+            room,
+            name,
+            value: 50.0,
+        });
+        execute(&mut house, &set).unwrap();
+        let get = blind(|room, name| DeviceCommand::Get { room, name });
+        assert_eq!(execute(&mut house, &get).unwrap(), "Blind\tOPEN\t50\n");
+
+        execute(
+            &mut house,
+            &blind(|room, name| DeviceCommand::Off { room, name }),
+        )
+        .unwrap();
+        assert_eq!(execute(&mut house, &get).unwrap(), "Blind\tCLOSED\t0\n");
+    }
+
+    #[test]
+    fn test_run_saves_house_file() {
+        let path = file(false).file;
+        let _ = fs::remove_file(&path);
+        let err = run(&room(RoomCommand::Ls)).unwrap_err();
+        assert_eq!(exit_code(&err), EXIT_STORAGE);
+
+        run(&room(RoomCommand::Add {
+            name: "Attic".to_string(),
+        }))
+        .unwrap();
+        let output = run(&room(RoomCommand::Ls));
+        fs::remove_file(&path).unwrap();
+        assert_eq!(output.unwrap(), "Attic\n");
+
+        let rm = room(RoomCommand::Rm {
+            name: "Cellar".to_string(),
+        });
+        let err = execute(&mut House::new(), &rm).unwrap_err();
+        assert_eq!(exit_code(&err), EXIT_FAILED);
+    }
+
+    #[test]
+    fn test_run_on_house_with_thermometer() {
+        let file = FileArgs {
+            file: env::temp_dir().join(format!("smart_house_cli_{}_t.json", std::process::id())),
+            json: false,
+        };
+        let mut house = House::new();
+        house.add_room("Hall", None).unwrap();
+        let thermometer = create_device("Thermo", DeviceType::Thermometer, 21.5).unwrap();
+        house
+            .get_mut_room("Hall")
+            .unwrap()
+            .add_device("Thermo", thermometer)
+            .unwrap();
+        house.save(&file.file).unwrap();
+
+        let get = HouseCommand::Device {
+            file,
+            command: DeviceCommand::Get {
+                room: "Hall".to_string(),
+                name: "Thermo".to_string(),
+            },
+        };
+        let output = run(&get);
+        fs::remove_file(&get.file().file).unwrap();
+        assert!(output.unwrap().starts_with("Thermometer\tOFF\t"));
+
+        let err = SmartHouseError::Backend("no library".to_string());
+        assert_eq!(exit_code(&err), EXIT_BACKEND);
+    }
+}
@@ -1,158 +1,865 @@
+use repl::{Arg, COMMANDS, CommandSpec, ReplHelper, find_command, split_device, split_words};
+use rustyline::Editor;
+use rustyline::error::ReadlineError;
+use rustyline::history::DefaultHistory;
+use smart_house_lib::billing::{EnergyReport, Tariff, format_time, parse_date};
+use smart_house_lib::clock::unix_now;
+use smart_house_lib::error::SmartHouseError;
+use smart_house_lib::history::History;
 use smart_house_lib::house::House;
+use smart_house_lib::labels::Labels;
+use smart_house_lib::metrics::MetricsEndpoint;
+use smart_house_lib::query::DeviceQuery;
 use smart_house_lib::report::Reportable;
-use smart_house_lib::smart_device::SmartDevice;
-use std::io;
-use std::io::Write;
+use smart_house_lib::report::diff::ReportDiff;
+use smart_house_lib::report::render::ReportFormat;
+use smart_house_lib::report::{Column, HouseReport, ReportOptions};
+use smart_house_lib::smart_device::{self, SmartDevice};
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
 
-pub fn run_cli_loop(mut house: House) {
+pub mod commands;
+pub mod repl;
+
+pub const DEFAULT_HOUSE_FILE: &str = "house.json";
+const COMMAND_HISTORY_FILE: &str = ".smart_house_history";
+const PROMPT: &str = "smart_house> ";
+/// How often the simulation advances, history is recorded and metrics are republished
+/// while the shell waits for input.
+const REFRESH_PERIOD: Duration = Duration::from_secs(1);
+
+enum Flow {
+    Continue,
+    Exit,
+}
+
+#[derive(Debug)]
+enum CommandError {
+    Usage(&'static CommandSpec),
+    Failed(SmartHouseError),
+}
+
+impl From<SmartHouseError> for CommandError {
+    fn from(err: SmartHouseError) -> Self {
+        CommandError::Failed(err)
+    }
+}
+
+/// The house with everything that follows its state, shared with the refresh thread.
+struct Session {
+    house: House,
+    history: History,
+    metrics: Option<MetricsEndpoint>,
+    last_tick: Instant,
+}
+
+impl Session {
+    /// Advances the simulation to now, records the history and republishes the metrics.
+    fn refresh(&mut self) -> Result<usize, SmartHouseError> {
+        smart_device::tick(self.last_tick.elapsed().as_secs_f64());
+        self.last_tick = Instant::now();
+        if let Some(metrics) = &self.metrics {
+            metrics.publish(&self.house);
+        }
+        self.history.record_now(&self.house)
+    }
+}
+
+/// Refreshes the session every [`REFRESH_PERIOD`] until the shell drops it.
+fn spawn_refresh(session: &Arc<Mutex<Session>>) {
+    let session = Arc::downgrade(session);
+    thread::spawn(move || {
+        while let Some(shared) = session.upgrade() {
+            // A failed recording is reported by the shell before the next command.
+            let _ = shared.lock().unwrap().refresh();
+            drop(shared);
+            thread::sleep(REFRESH_PERIOD);
+        }
+    });
+}
+
+pub fn run_cli_loop(house: House, metrics: Option<MetricsEndpoint>, history: History) {
+    let mut editor = match Editor::<ReplHelper, DefaultHistory>::new() {
+        Ok(editor) => editor,
+        Err(err) => {
+            println!("Line editor not started: {}.", err);
+            return;
+        }
+    };
+    editor.set_helper(Some(ReplHelper::default()));
+    let history_path = command_history_path();
+    // A missing file just means a fresh history.
+    let _ = editor.load_history(&history_path);
+    println!("Type 'help' for commands, Tab to complete names.");
+
+    let session = Arc::new(Mutex::new(Session {
+        house,
+        history,
+        metrics,
+        last_tick: Instant::now(),
+    }));
+    spawn_refresh(&session);
     loop {
-        println!("\nCommands:");
-        println!("  1 - Add device");
-        println!("  2 - Remove device");
-        println!("  3 - Tern on device");
-        println!("  4 - Tern off device");
-        println!("  5 - Show report");
-        println!("  0 - Exit");
-        print!("\nEnter command (0-5): ");
-        io::stdout().flush().unwrap();
-
-        let mut input = String::new();
-        if io::stdin().read_line(&mut input).is_err() {
-            println!("Error reading input.");
+        {
+            let mut session = session.lock().unwrap();
+            if let Err(err) = session.refresh() {
+                println!("History not recorded: {}.", err);
+            }
+            if let Some(helper) = editor.helper_mut() {
+                helper.refresh(&session.house);
+            }
+        }
+
+        let line = match editor.readline(PROMPT) {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => {
+                println!("Goodbye!");
+                break;
+            }
+            Err(err) => {
+                println!("Input not read: {}.", err);
+                break;
+            }
+        };
+        if line.trim().is_empty() {
             continue;
         }
+        let secret = split_words(&line)
+            .ok()
+            .and_then(|words| find_command(words.first()?))
+            .is_some_and(|command| command.args.contains(&Arg::Secret));
+        if !secret {
+            let _ = editor.add_history_entry(line.as_str());
+        }
 
-        let command = input.trim().parse::<u8>().unwrap_or(255);
-        input.clear();
-        match command {
-            1 => {
-                // Add device
-                let room_name = get_name(&house.get_rooms_names());
-                if room_name.is_empty() {
-                    println!("Invalid room number.");
-                    continue;
-                }
+        let mut session = session.lock().unwrap();
+        if let Err(err) = session.refresh() {
+            println!("History not recorded: {}.", err);
+        }
+        let Session { house, history, .. } = &mut *session;
+        // Not added to the command history, unlike the lines read above.
+        let mut read_secret = |prompt: &str| editor.readline(prompt).ok();
+        match execute(house, history, &line, &mut read_secret) {
+            Ok(Flow::Continue) => {}
+            Ok(Flow::Exit) => {
+                println!("Goodbye!");
+                break;
+            }
+            Err(CommandError::Usage(command)) => println!("Usage: {}", command.usage),
+            Err(CommandError::Failed(err)) => println!("{}.", err),
+        }
 
-                println!("Select device type:");
-                println!("  1 - Thermometer");
-                println!("  2 - Power Socket");
-                print!("Choose (1-2): ");
-                io::stdout().flush().unwrap();
-                input.clear();
-                io::stdin().read_line(&mut input).unwrap();
-                let dev_type = input.trim().parse::<u8>().unwrap_or(0);
-
-                let (mut device_name, device) = match dev_type {
-                    1 => {
-                        let device_name = "Thermometer".to_string();
-                        (device_name, SmartDevice::thermometer(21.5f64))
-                    }
-                    2 => {
-                        let device_name = "PowerSocket".to_string();
-                        (device_name, SmartDevice::power_socket(50.0f64))
-                    }
-                    _ => {
-                        println!("Invalid device type.");
-                        continue;
-                    }
-                };
+        if let Some(event) = house.check_alarms() {
+            println!("\n!!! ALARM !!!");
+            for (room_name, device_name) in &event.sources {
+                println!("Triggered by '{}' in room '{}'.", device_name, room_name);
+            }
+            for (room_name, device_name) in &event.switched_off {
+                println!(
+                    "Device '{}' in room '{}' switched off.",
+                    device_name, room_name
+                );
+            }
+        }
+    }
+
+    if let Err(err) = editor.save_history(&history_path) {
+        println!("Command history not saved: {}.", err);
+    }
+}
+
+/// `~/.smart_house_history`, or the working directory without a home.
+fn command_history_path() -> PathBuf {
+    env::var_os("HOME")
+        .map(PathBuf::from)
+        .unwrap_or_default()
+        .join(COMMAND_HISTORY_FILE)
+}
+
+/// Arguments of one command, checked against its usage.
+struct Args<'a> {
+    command: &'static CommandSpec,
+    words: &'a [String],
+}
+
+impl<'a> Args<'a> {
+    fn usage(&self) -> CommandError {
+        CommandError::Usage(self.command)
+    }
+
+    fn get(&self, index: usize) -> Result<&'a str, CommandError> {
+        self.opt(index).ok_or_else(|| self.usage())
+    }
+
+    fn opt(&self, index: usize) -> Option<&'a str> {
+        self.words.get(index).map(String::as_str)
+    }
+
+    fn parse<T: FromStr>(&self, index: usize) -> Result<T, CommandError> {
+        self.get(index)?.parse().map_err(|_| self.usage())
+    }
+
+    fn device(&self, index: usize) -> Result<(&'a str, &'a str), CommandError> {
+        split_device(self.get(index)?).ok_or_else(|| self.usage())
+    }
+
+    /// `key=value` options and bare flags from `index` on.
+    fn options(&self, index: usize) -> Vec<(&'a str, Option<&'a str>)> {
+        self.words
+            .iter()
+            .skip(index)
+            .map(|word| match word.split_once('=') {
+                Some((key, value)) => (key, Some(value)),
+                None => (word.as_str(), None),
+            })
+            .collect()
+    }
+
+    fn option<T: FromStr>(&self, value: Option<&str>) -> Result<T, CommandError> {
+        value
+            .and_then(|value| value.parse().ok())
+            .ok_or_else(|| self.usage())
+    }
+}
+
+/// Runs one command line; `read_secret` asks for a PIN that was left out.
+fn execute(
+    house: &mut House,
+    history: &History,
+    line: &str,
+    read_secret: &mut dyn FnMut(&str) -> Option<String>,
+) -> Result<Flow, CommandError> {
+    let words = split_words(line)?;
+    let Some((name, words)) = words.split_first() else {
+        return Ok(Flow::Continue);
+    };
+    let command = find_command(name).ok_or_else(|| {
+        SmartHouseError::InvalidParameter(format!("unknown command '{}', try 'help'", name))
+    })?;
+    let args = Args { command, words };
+    if !command.takes_options() && words.len() > command.args.len() {
+        return Err(args.usage());
+    }
 
-                if let Some(room) = house.get_mut_room(&room_name) {
-                    device_name = format!("{}_{}", device_name, room.get_devices_names().len() + 1);
-                    room.add_device(&device_name, device);
-                    println!("Device '{}' added to room '{}'.", device_name, room_name);
+    match command.name {
+        "help" => match args.opt(0) {
+            None => {
+                for command in COMMANDS {
+                    println!("  {:12} {}", command.name, command.about);
                 }
             }
-            2 => {
-                // Remove device
-                let room_name = get_name(&house.get_rooms_names());
-                if room_name.is_empty() {
-                    println!("Invalid room number.");
+            Some(name) => {
+                let command = find_command(name).ok_or_else(|| {
+                    SmartHouseError::InvalidParameter(format!("unknown command '{}'", name))
+                })?;
+                println!("Usage: {}\n{}.", command.usage, command.about);
+                if command.args.contains(&Arg::Device) {
+                    println!("Quote names with spaces: \"Second room\"/Thermometer_1.");
+                }
+            }
+        },
+        "ls" => {
+            for (room_name, room) in house.rooms() {
+                if args.opt(0).is_some_and(|name| name != room_name) {
                     continue;
                 }
-
-                if let Some(room) = house.get_mut_room(&room_name) {
-                    let device_name = get_name(&room.get_devices_names());
-                    if device_name.is_empty() {
-                        println!("Invalid device number.");
-                        continue;
-                    }
-                    room.del_device(&device_name);
-                    println!(
-                        "Device '{}' removed from room '{}'.",
-                        device_name, room_name
-                    );
+                println!("{}", room_name);
+                for (device_name, device) in room.devices() {
+                    println!("  {:18}{}", device_name, device.generate_report());
                 }
             }
-            3 => {
-                // Tern on
-                let room_name = get_name(&house.get_rooms_names());
-                if room_name.is_empty() {
-                    println!("Invalid room number.");
-                    continue;
+        }
+        "add" => {
+            let (room_name, device_name) = args.device(0)?;
+            let kind = args.get(1)?.parse()?;
+            let value = match args.opt(2) {
+                Some(_) => args.parse(2)?,
+                None => 0.0,
+            };
+            let device = commands::create_device(device_name, kind, value)?;
+            commands::room_mut(house, room_name)?.add_device(device_name, device)?;
+            println!("Device '{}' added to room '{}'.", device_name, room_name);
+        }
+        "rm" => {
+            let (room_name, device_name) = args.device(0)?;
+            house.del_device(room_name, device_name)?;
+            println!(
+                "Device '{}' removed from room '{}'.",
+                device_name, room_name
+            );
+        }
+        "on" | "off" => {
+            let (room_name, device_name) = args.device(0)?;
+            let on = command.name == "on";
+            commands::device_mut(house, room_name, device_name)?.switch(on)?;
+            println!("Device '{}' turned {}.", device_name, command.name);
+        }
+        "set" => {
+            let (room_name, device_name) = args.device(0)?;
+            let value = args.parse::<f64>(1)?;
+            let device = commands::device_mut(house, room_name, device_name)?;
+            if !device.set_value(value) {
+                return Err(SmartHouseError::Unsupported(format!(
+                    "{:?} does not accept value {}",
+                    device.device_type(),
+                    value
+                ))
+                .into());
+            }
+            println!("Device '{}' set to {}.", device_name, value);
+        }
+        "lock" => {
+            let device = lock_mut(house, &args)?;
+            match device.lock() {
+                true => println!("Door locked."),
+                false => println!("Door is jammed."),
+            }
+        }
+        "unlock" => {
+            // Check the lock before asking for the PIN.
+            lock_mut(house, &args)?;
+            let pin = secret_arg(&args, 1, read_secret)?;
+            let device = lock_mut(house, &args)?;
+            match device.unlock(&pin) {
+                true => println!("Door unlocked."),
+                false => println!("Wrong PIN or door is jammed."),
+            }
+        }
+        "code" => {
+            let user = args.get(1)?;
+            // Check the lock before asking for the PIN.
+            lock_mut(house, &args)?;
+            let pin = secret_arg(&args, 2, read_secret)?;
+            let device = lock_mut(house, &args)?;
+            match device.add_code(user, &pin) {
+                true => println!("PIN code for '{}' added.", user),
+                false => println!("PIN code rejected."),
+            }
+        }
+        "remove-code" => {
+            // Check the lock before asking for the PIN.
+            lock_mut(house, &args)?;
+            let pin = secret_arg(&args, 1, read_secret)?;
+            let device = lock_mut(house, &args)?;
+            match device.remove_code(&pin) {
+                true => println!("PIN code removed."),
+                false => println!("No such PIN code."),
+            }
+        }
+        "jam" => {
+            let jammed = match args.get(1)? {
+                "on" => true,
+                "off" => false,
+                _ => return Err(args.usage()),
+            };
+            lock_mut(house, &args)?.set_jammed(jammed);
+            match jammed {
+                true => println!("Door is jammed."),
+                false => println!("Door is free."),
+            }
+        }
+        "audit" => {
+            let trail = lock_mut(house, &args)?.audit_trail();
+            if trail.is_empty() {
+                println!("No unlocks recorded.");
+            }
+            for entry in trail {
+                println!("{}\t{}", format_time(entry.timestamp), entry.user);
+            }
+        }
+        "blind" => {
+            let (room_name, device_name) = args.device(0)?;
+            let action = args.get(1)?;
+            let device = commands::device_mut(house, room_name, device_name)?;
+            if !device.is_blind() {
+                return Err(not_a(device_name, "blind"));
+            }
+            let accepted = match action {
+                "open" => device.open(),
+                "close" => device.close(),
+                "stop" => device.stop(),
+                position => match position.parse::<f64>() {
+                    Ok(position) => device.set_position(position),
+                    Err(_) => return Err(args.usage()),
+                },
+            };
+            match accepted {
+                true => println!("Blind is moving to {}%.", device.target_position()),
+                false => println!("Invalid blind command."),
+            }
+        }
+        "blind-speed" => {
+            let (room_name, device_name) = args.device(0)?;
+            let speed = args.parse::<f64>(1)?;
+            let device = commands::device_mut(house, room_name, device_name)?;
+            if !device.is_blind() {
+                return Err(not_a(device_name, "blind"));
+            }
+            match device.set_speed(speed) {
+                true => println!("Blind moves at {}% per second.", speed),
+                false => println!("Speed must be a positive number."),
+            }
+        }
+        "trigger" => {
+            let (room_name, device_name) = args.device(0)?;
+            let device = commands::device_mut(house, room_name, device_name)?;
+            if !device.is_detector() {
+                return Err(not_a(device_name, "smoke detector"));
+            }
+            if !device.trigger_alarm() {
+                println!("Detector is switched off.");
+            }
+        }
+        "ack" => match house.acknowledge_alarm() {
+            Some(_) => println!("Alarm acknowledged, detectors silenced."),
+            None => println!("No active alarm."),
+        },
+        "add-room" => {
+            let room_name = args.get(0)?;
+            if room_name.trim().is_empty() {
+                return Err(SmartHouseError::EmptyName("room".to_string()).into());
+            }
+            house.add_room(room_name, None)?;
+            println!("Room '{}' added.", room_name);
+        }
+        "rm-room" => {
+            let room_name = args.get(0)?;
+            house.del_room(room_name)?;
+            println!("Room '{}' removed.", room_name);
+        }
+        "rename" => {
+            let (room_name, device_name) = args.device(0)?;
+            let new_name = args.get(1)?;
+            house.rename_device(room_name, device_name, new_name)?;
+            println!("Device '{}' renamed to '{}'.", device_name, new_name);
+        }
+        "rename-room" => {
+            let (room_name, new_name) = (args.get(0)?, args.get(1)?);
+            house.rename_room(room_name, new_name)?;
+            println!("Room '{}' renamed to '{}'.", room_name, new_name);
+        }
+        "copy-room" => {
+            let (room_name, new_name) = (args.get(0)?, args.get(1)?);
+            house.duplicate_room(room_name, new_name)?;
+            println!("Room '{}' copied to '{}'.", room_name, new_name);
+        }
+        "move" => {
+            let (room_name, device_name) = args.device(0)?;
+            let target = args.get(1)?;
+            house.move_device(room_name, device_name, target)?;
+            println!("Device '{}' moved to room '{}'.", device_name, target);
+        }
+        "move-room" => {
+            let room_name = args.get(0)?;
+            house.move_room(room_name, position(&args, 1)?)?;
+            println!("Room '{}' moved.", room_name);
+        }
+        "reorder" => {
+            let (room_name, device_name) = args.device(0)?;
+            let position = position(&args, 1)?;
+            commands::room_mut(house, room_name)?.move_device(device_name, position)?;
+            println!("Device '{}' moved.", device_name);
+        }
+        "label" => {
+            let (room_name, device_name) = args.device(0)?;
+            let label = args.get(1)?;
+            let device = commands::device_mut(house, room_name, device_name)?;
+            edit_labels(device.labels_mut(), label);
+        }
+        "label-room" => {
+            let label = args.get(1)?;
+            edit_labels(commands::room_mut(house, args.get(0)?)?.labels_mut(), label);
+        }
+        "find" => {
+            let mut query = DeviceQuery::new();
+            for (key, value) in args.options(0) {
+                query = match (key, value) {
+                    ("type", value) => query.of_type(args.option(value)?),
+                    ("on", None) => query.on(),
+                    ("off", None) => query.off(),
+                    ("tag", Some(tag)) => query.tagged(tag),
+                    ("min", value) => query.min_value(args.option(value)?),
+                    ("max", value) => query.max_value(args.option(value)?),
+                    _ => return Err(args.usage()),
+                };
+            }
+            let mut found = 0;
+            for (room_name, device_name, device) in house.query(&query) {
+                println!(
+                    "{:14}{:14}{}",
+                    room_name,
+                    device_name,
+                    device.generate_report()
+                );
+                found += 1;
+            }
+            println!("{} device(s) found.", found);
+        }
+        "group-new" => {
+            let name = args.get(0)?;
+            house.create_group(name)?;
+            println!("Group '{}' created.", name);
+        }
+        "group-add" => {
+            let group_name = args.get(0)?;
+            let (room_name, device_name) = args.device(1)?;
+            house.add_to_group(group_name, room_name, device_name)?;
+            println!("Device '{}' added to group '{}'.", device_name, group_name);
+        }
+        "group" => {
+            let group_name = args.get(0)?;
+            let outcomes = match args.get(1)? {
+                "on" => house.group_turn_on(group_name)?,
+                "off" => house.group_turn_off(group_name)?,
+                _ => house.group_set_value(group_name, args.parse(1)?)?,
+            };
+            for outcome in outcomes {
+                match outcome.result {
+                    Ok(()) => println!("  {}/{}: ok", outcome.room, outcome.device),
+                    Err(err) => println!("  {}/{}: {}", outcome.room, outcome.device, err),
                 }
-                if let Some(room) = house.get_mut_room(&room_name) {
-                    let device_name = get_name(&room.get_devices_names());
-                    if device_name.is_empty() {
-                        println!("Invalid device number.");
-                        continue;
-                    }
-                    if let Some(device) = room.get_mut_device(&device_name) {
-                        device.turn_on();
-                        println!("Device '{}' turned on.", device_name);
+            }
+        }
+        "capture" => {
+            let name = args.get(0)?;
+            let devices = match args.opt(1) {
+                Some(group_name) => house
+                    .get_group(group_name)
+                    .ok_or_else(|| SmartHouseError::GroupNotFound(group_name.to_string()))?
+                    .to_vec(),
+                None => house
+                    .devices()
+                    .map(|(room, device, _)| (room.to_string(), device.to_string()))
+                    .collect(),
+            };
+            house.capture_scene(name, &devices)?;
+            println!("Scene '{}' captured ({} devices).", name, devices.len());
+        }
+        "apply" => {
+            let dry_run = match args.opt(1) {
+                None => false,
+                Some("dry-run") => true,
+                Some(_) => return Err(args.usage()),
+            };
+            let changes = house.apply_scene(args.get(0)?, dry_run)?;
+            if changes.is_empty() {
+                println!("Nothing to change.");
+            }
+            for change in changes {
+                let mut line = format!("  {}/{}:", change.room, change.device);
+                if let Some((from, to)) = change.on {
+                    line += &format!(" {} -> {}", on_off(from), on_off(to));
+                }
+                if let Some((from, to)) = change.value {
+                    line += &format!(" value {} -> {}", from, to);
+                }
+                if let Err(err) = change.result {
+                    line += &format!(" ({})", err);
+                }
+                println!("{}", line);
+            }
+        }
+        "report" => {
+            let mut format = ReportFormat::Text;
+            let mut options = ReportOptions::new();
+            for (key, value) in args.options(0) {
+                options = match (key, value) {
+                    ("format", Some(value)) => {
+                        format = value.parse()?;
+                        options
                     }
+                    ("columns", Some(columns)) => options.columns(
+                        &columns
+                            .split(',')
+                            .map(str::parse::<Column>)
+                            .collect::<Result<Vec<Column>, SmartHouseError>>()?,
+                    ),
+                    ("sort", Some(sort)) => match sort.strip_prefix('-') {
+                        Some(key) => options.sort_by(key.parse()?).descending(),
+                        None => options.sort_by(sort.parse()?),
+                    },
+                    ("on", None) => options.only_on(),
+                    ("room", Some(room_name)) => options.in_room(room_name),
+                    ("type", value) => options.of_type(args.option(value)?),
+                    ("width", value) => options.max_width(args.option(value)?),
+                    _ => return Err(args.usage()),
+                };
+            }
+            let report = format
+                .renderer()
+                .render(&HouseReport::from_house(house), &options);
+            print!("{}", commands::terminated(report));
+        }
+        "export" => {
+            let format = match args.opt(0) {
+                Some(format) => format.parse()?,
+                None => ReportFormat::Html,
+            };
+            let path = args
+                .opt(1)
+                .map(str::to_string)
+                .unwrap_or_else(|| format!("house_report.{}", format.extension()));
+            let report = format
+                .renderer()
+                .render(&HouseReport::from_house(house), &ReportOptions::default());
+            fs::write(&path, report).map_err(|err| SmartHouseError::Storage(err.to_string()))?;
+            println!("Report written to '{}'.", path);
+        }
+        "diff" => {
+            let snapshot = load_snapshot(args.get(0)?)?;
+            let mut threshold = 0.0;
+            let mut json = false;
+            for (key, value) in args.options(1) {
+                match (key, value) {
+                    ("threshold", value) => threshold = args.option(value)?,
+                    ("json", None) => json = true,
+                    _ => return Err(args.usage()),
                 }
             }
-            4 => {
-                // Tern off
-                let room_name = get_name(&house.get_rooms_names());
-                if room_name.is_empty() {
-                    println!("Invalid room number.");
-                    continue;
+            let diff = ReportDiff::between(&snapshot, &HouseReport::from_house(house), threshold);
+            match json {
+                true => println!("{}", diff.to_json()?),
+                false => print!("{}", diff),
+            }
+        }
+        "history" => {
+            let (room_name, device_name) = args.device(0)?;
+            house.get_device(room_name, device_name)?;
+            let mut minutes = 60;
+            let mut bucket = None;
+            for (key, value) in args.options(1) {
+                match (key, value) {
+                    ("minutes", value) => minutes = args.option(value)?,
+                    ("bucket", value) => bucket = Some(args.option::<u64>(value)?),
+                    _ => return Err(args.usage()),
                 }
-                if let Some(room) = house.get_mut_room(&room_name) {
-                    let device_name = get_name(&room.get_devices_names());
-                    if device_name.is_empty() {
-                        println!("Invalid device number.");
-                        continue;
+            }
+            let to = unix_now();
+            let from = to.saturating_sub(minutes * 60);
+            match bucket {
+                Some(length) => {
+                    println!(
+                        "{:>12}{:>10}{:>10}{:>10}{:>8}",
+                        "Start", "Min", "Avg", "Max", "Count"
+                    );
+                    for bucket in history.downsample(room_name, device_name, from, to, length) {
+                        println!(
+                            "{:>12}{:>10.2}{:>10.2}{:>10.2}{:>8}",
+                            bucket.start, bucket.min, bucket.avg, bucket.max, bucket.count
+                        );
                     }
-                    if let Some(device) = room.get_mut_device(&device_name) {
-                        device.turn_off();
-                        println!("Device '{}' turned on.", device_name);
+                }
+                None => {
+                    println!("{:>12}{:>10}{:>6}", "Time", "Value", "On");
+                    for sample in history.query(room_name, device_name, from, to) {
+                        println!(
+                            "{:>12}{:>10.2}{:>6}",
+                            sample.time, sample.value, sample.is_on
+                        );
                     }
                 }
             }
-            5 => {
-                print_report(&house);
-            }
-            0 => {
-                println!("Goodbye!");
-                break;
+        }
+        "energy" => {
+            let to = unix_now();
+            let mut period = (to.saturating_sub(30 * 86400), to);
+            let mut tariff = Tariff::Flat(0.25);
+            let mut format = ReportFormat::Text;
+            for (key, value) in args.options(0) {
+                match (key, value) {
+                    ("from", Some(date)) => period.0 = parse_date(date)?,
+                    ("to", Some(date)) => period.1 = parse_date(date)?,
+                    ("tariff", Some(value)) => tariff = value.parse()?,
+                    ("format", Some(value)) => format = value.parse()?,
+                    _ => return Err(args.usage()),
+                }
             }
-            _ => {
-                println!("Invalid command. Use 0-5.");
+            let report = EnergyReport::compute(house, history, period.0, period.1, &tariff)?;
+            print!("{}", commands::terminated(report.render(format)?));
+        }
+        "save" => {
+            let path = args.opt(0).unwrap_or(DEFAULT_HOUSE_FILE);
+            house.save(path)?;
+            println!("House saved to '{}'.", path);
+        }
+        "load" => {
+            let path = args.opt(0).unwrap_or(DEFAULT_HOUSE_FILE);
+            let (loaded, warnings) = House::load_with_warnings(path)?;
+            *house = loaded;
+            for warning in warnings {
+                println!("Warning: {}.", warning);
             }
+            println!("House loaded from '{}'.", path);
         }
+        "exit" => return Ok(Flow::Exit),
+        name => unreachable!("command '{}' has no handler", name),
     }
+    Ok(Flow::Continue)
 }
 
-fn print_report<T: Reportable>(x: &T) {
-    println!("{}", x.generate_report());
+/// The PIN at `index`, or one read at a separate prompt.
+fn secret_arg(
+    args: &Args,
+    index: usize,
+    read_secret: &mut dyn FnMut(&str) -> Option<String>,
+) -> Result<String, CommandError> {
+    match args.opt(index) {
+        Some(pin) => Ok(pin.to_string()),
+        None => read_secret("Enter PIN: ")
+            .map(|pin| pin.trim().to_string())
+            .ok_or_else(|| args.usage()),
+    }
 }
 
-fn get_name(names_list: &[String]) -> String {
-    println!("Available:");
-    for (i, name) in names_list.iter().enumerate() {
-        println!("  {}: {}", i + 1, name);
+fn lock_mut<'a>(house: &'a mut House, args: &Args) -> Result<&'a mut SmartDevice, CommandError> {
+    let (room_name, device_name) = args.device(0)?;
+    let device = commands::device_mut(house, room_name, device_name)?;
+    match device.is_lock() {
+        true => Ok(device),
+        false => Err(not_a(device_name, "door lock")),
+    }
+}
+
+fn not_a(device_name: &str, kind: &str) -> CommandError {
+    SmartHouseError::Unsupported(format!("'{}' is not a {}", device_name, kind)).into()
+}
+
+/// Reads a position counted from 1.
+fn position(args: &Args, index: usize) -> Result<usize, CommandError> {
+    match args.parse::<usize>(index)? {
+        0 => Err(args.usage()),
+        position => Ok(position - 1),
+    }
+}
+
+fn edit_labels(labels: &mut Labels, input: &str) {
+    let (remove, label) = match input.strip_prefix('-') {
+        Some(label) => (true, label),
+        None => (false, input),
+    };
+    match (remove, label.split_once('=')) {
+        (_, _) if label.is_empty() => println!("Nothing to change."),
+        (false, Some((key, value))) => {
+            labels.set_meta(key.trim(), value.trim());
+        }
+        (true, Some((key, _))) => {
+            labels.remove_meta(key.trim());
+        }
+        (false, None) => {
+            labels.add_tag(label);
+        }
+        (true, None) => {
+            labels.remove_tag(label);
+        }
+    }
+    println!("Labels: {}", labels);
+}
+
+fn on_off(on: bool) -> &'static str {
+    match on {
+        true => "ON",
+        false => "OFF",
+    }
+}
+
+/// Accepts both a report written as JSON and a saved house.
+fn load_snapshot(path: &str) -> Result<HouseReport, SmartHouseError> {
+    let json = fs::read_to_string(path).map_err(|err| SmartHouseError::Storage(err.to_string()))?;
+    HouseReport::from_json(&json)
+        .or_else(|_| House::from_json(&json).map(|house| HouseReport::from_house(&house)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use smart_house_lib::house;
+
+    fn run(house: &mut House, line: &str) -> Result<Flow, CommandError> {
+        execute(house, &History::new(10, 60), line, &mut |_| None)
+    }
+
+    #[test]
+    fn test_execute_named_commands() {
+        let mut house = house! {
+            "Second room" {
+                "Lamp_1": power_socket(60.0),
+                "Door": door_lock(),
+                "Blind": blind(0.0)
+            }
+        };
+        assert!(run(&mut house, "on \"Second room\"/Lamp_1").is_ok());
+        assert!(house.get_device("Second room", "Lamp_1").unwrap().is_on());
+
+        run(&mut house, "add-room Hall").unwrap();
+        run(&mut house, "move 'Second room/Lamp_1' Hall").unwrap();
+        run(&mut house, "label Hall/Lamp_1 floor=1").unwrap();
+        let lamp = house.get_device("Hall", "Lamp_1").unwrap();
+        assert_eq!(lamp.labels().get_meta("floor"), Some("1"));
+        let history = History::new(10, 60);
+        let mut pin = |_: &str| Some("1234".to_string());
+        execute(
+            &mut house,
+            &history,
+            "code 'Second room/Door' alice",
+            &mut pin,
+        )
+        .unwrap();
+        execute(&mut house, &history, "lock 'Second room/Door'", &mut pin).unwrap();
+        execute(&mut house, &history, "unlock 'Second room/Door'", &mut pin).unwrap();
+        assert_eq!(
+            house.get_device("Second room", "Door").unwrap().get_state(),
+            "UNLOCKED"
+        );
+        assert!(matches!(
+            run(&mut house, "unlock 'Second room/Door'"),
+            Err(CommandError::Usage(_))
+        ));
+        execute(&mut house, &history, "audit 'Second room/Door'", &mut pin).unwrap();
+        execute(&mut house, &history, "jam 'Second room/Door' on", &mut pin).unwrap();
+        let door = house.get_device("Second room", "Door").unwrap();
+        assert!(door.is_jammed());
+        execute(&mut house, &history, "jam 'Second room/Door' off", &mut pin).unwrap();
+        execute(
+            &mut house,
+            &history,
+            "remove-code 'Second room/Door'",
+            &mut pin,
+        )
+        .unwrap();
+        let door = house.get_device("Second room", "Door").unwrap();
+        assert!(door.pin_codes().is_empty());
+        run(&mut house, "blind-speed 'Second room/Blind' 25").unwrap();
+        assert_eq!(
+            house.get_device("Second room", "Blind").unwrap().speed(),
+            25.0
+        );
+        assert!(matches!(run(&mut house, "quit"), Ok(Flow::Exit)));
+        assert!(matches!(run(&mut house, "   "), Ok(Flow::Continue)));
     }
 
-    print!("Select number: ");
-    io::stdout().flush().unwrap();
-    let mut input = String::new();
-    io::stdin().read_line(&mut input).unwrap();
-    let idx = input.trim().parse::<usize>().unwrap_or(0);
-    if idx == 0 || idx > names_list.len() {
-        return String::new();
+    #[test]
+    fn test_execute_errors() {
+        let mut house = House::new();
+        let usage = |result| match result {
+            Err(CommandError::Usage(command)) => command.name,
+            _ => "",
+        };
+        assert_eq!(usage(run(&mut house, "on Lamp_1")), "on");
+        assert_eq!(usage(run(&mut house, "on Hall/Lamp_1 now")), "on");
+        assert_eq!(usage(run(&mut house, "find colour=red")), "find");
+        assert!(matches!(
+            run(&mut house, "off Hall/Lamp_1"),
+            Err(CommandError::Failed(SmartHouseError::RoomNotFound(_)))
+        ));
+        assert!(matches!(
+            run(&mut house, "dance"),
+            Err(CommandError::Failed(SmartHouseError::InvalidParameter(_)))
+        ));
     }
-    names_list[idx - 1].clone()
 }
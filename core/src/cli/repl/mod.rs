@@ -0,0 +1,532 @@
+use rustyline::Context;
+use rustyline::Helper;
+use rustyline::completion::{Completer, Pair};
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::validate::Validator;
+use smart_house_lib::error::SmartHouseError;
+use smart_house_lib::house::House;
+use smart_house_lib::smart_device::DeviceType;
+
+/// What a command argument refers to, used for tab completion.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Arg {
+    /// `room/device`
+    Device,
+    Room,
+    Group,
+    Scene,
+    Type,
+    Format,
+    Text,
+    /// A PIN, asked for at a separate prompt when left out; lines with one are not
+    /// kept in the command history.
+    Secret,
+    /// Any number of `key=value` options and flags.
+    Options,
+}
+
+#[derive(Debug)]
+pub struct CommandSpec {
+    pub name: &'static str,
+    pub usage: &'static str,
+    pub about: &'static str,
+    pub args: &'static [Arg],
+}
+
+impl CommandSpec {
+    pub fn takes_options(&self) -> bool {
+        self.args.last() == Some(&Arg::Options)
+    }
+}
+
+const fn command(
+    name: &'static str,
+    usage: &'static str,
+    about: &'static str,
+    args: &'static [Arg],
+) -> CommandSpec {
+    CommandSpec {
+        name,
+        usage,
+        about,
+        args,
+    }
+}
+
+pub const COMMANDS: &[CommandSpec] = &[
+    command(
+        "help",
+        "help [command]",
+        "List commands or describe one",
+        &[Arg::Text],
+    ),
+    command("ls", "ls [room]", "List rooms and devices", &[Arg::Room]),
+    command(
+        "add",
+        "add <room>/<device> <type> [value]",
+        "Add a device; value is the wattage, temperature or blind position",
+        &[Arg::Device, Arg::Type, Arg::Text],
+    ),
+    command(
+        "rm",
+        "rm <room>/<device>",
+        "Remove a device",
+        &[Arg::Device],
+    ),
+    command(
+        "on",
+        "on <room>/<device>",
+        "Switch a device on, or lock a door",
+        &[Arg::Device],
+    ),
+    command(
+        "off",
+        "off <room>/<device>",
+        "Switch a device off",
+        &[Arg::Device],
+    ),
+    command(
+        "set",
+        "set <room>/<device> <value>",
+        "Set the wattage, temperature or blind position",
+        &[Arg::Device, Arg::Text],
+    ),
+    command(
+        "lock",
+        "lock <room>/<device>",
+        "Lock a door",
+        &[Arg::Device],
+    ),
+    command(
+        "unlock",
+        "unlock <room>/<device> [pin]",
+        "Unlock a door with a PIN, asked for if left out",
+        &[Arg::Device, Arg::Secret],
+    ),
+    command(
+        "code",
+        "code <room>/<device> <user> [pin]",
+        "Add a PIN code to a door lock, asking for the PIN if left out",
+        &[Arg::Device, Arg::Text, Arg::Secret],
+    ),
+    command(
+        "remove-code",
+        "remove-code <room>/<device> [pin]",
+        "Remove a PIN code from a door lock, asking for the PIN if left out",
+        &[Arg::Device, Arg::Secret],
+    ),
+    command(
+        "jam",
+        "jam <room>/<device> <on|off>",
+        "Simulate a jammed door lock, or free it",
+        &[Arg::Device, Arg::Text],
+    ),
+    command(
+        "audit",
+        "audit <room>/<device>",
+        "Show who unlocked a door and when",
+        &[Arg::Device],
+    ),
+    command(
+        "blind",
+        "blind <room>/<device> <open|close|stop|position>",
+        "Move a blind; position is 0-100",
+        &[Arg::Device, Arg::Text],
+    ),
+    command(
+        "blind-speed",
+        "blind-speed <room>/<device> <speed>",
+        "Set how fast a blind moves, in percent per second",
+        &[Arg::Device, Arg::Text],
+    ),
+    command(
+        "trigger",
+        "trigger <room>/<device>",
+        "Test a smoke detector",
+        &[Arg::Device],
+    ),
+    command(
+        "ack",
+        "ack",
+        "Acknowledge the alarm and silence detectors",
+        &[],
+    ),
+    command(
+        "add-room",
+        "add-room <room>",
+        "Add an empty room",
+        &[Arg::Room],
+    ),
+    command(
+        "rm-room",
+        "rm-room <room>",
+        "Remove a room with all its devices",
+        &[Arg::Room],
+    ),
+    command(
+        "rename",
+        "rename <room>/<device> <name>",
+        "Rename a device",
+        &[Arg::Device, Arg::Text],
+    ),
+    command(
+        "rename-room",
+        "rename-room <room> <name>",
+        "Rename a room",
+        &[Arg::Room, Arg::Text],
+    ),
+    command(
+        "copy-room",
+        "copy-room <room> <name>",
+        "Copy a room with fresh devices",
+        &[Arg::Room, Arg::Text],
+    ),
+    command(
+        "move",
+        "move <room>/<device> <room>",
+        "Move a device to another room",
+        &[Arg::Device, Arg::Room],
+    ),
+    command(
+        "move-room",
+        "move-room <room> <position>",
+        "Move a room to a position, counting from 1",
+        &[Arg::Room, Arg::Text],
+    ),
+    command(
+        "reorder",
+        "reorder <room>/<device> <position>",
+        "Move a device within its room, counting from 1",
+        &[Arg::Device, Arg::Text],
+    ),
+    command(
+        "label",
+        "label <room>/<device> <tag|key=value|-tag|-key>",
+        "Add or remove a device label",
+        &[Arg::Device, Arg::Text],
+    ),
+    command(
+        "label-room",
+        "label-room <room> <tag|key=value|-tag|-key>",
+        "Add or remove a room label",
+        &[Arg::Room, Arg::Text],
+    ),
+    command(
+        "find",
+        "find [type=T] [on|off] [tag=T] [min=N] [max=N]",
+        "Find devices matching all conditions",
+        &[Arg::Options],
+    ),
+    command(
+        "group-new",
+        "group-new <group>",
+        "Create a device group",
+        &[Arg::Text],
+    ),
+    command(
+        "group-add",
+        "group-add <group> <room>/<device>",
+        "Add a device to a group",
+        &[Arg::Group, Arg::Device],
+    ),
+    command(
+        "group",
+        "group <group> <on|off|value>",
+        "Switch or set every device of a group",
+        &[Arg::Group, Arg::Text],
+    ),
+    command(
+        "capture",
+        "capture <scene> [group]",
+        "Capture a scene of a group or the whole house",
+        &[Arg::Scene, Arg::Group],
+    ),
+    command(
+        "apply",
+        "apply <scene> [dry-run]",
+        "Apply a scene, or only show its changes",
+        &[Arg::Scene, Arg::Text],
+    ),
+    command(
+        "report",
+        "report [format=F] [columns=C,..] [sort=[-]K] [on] [room=R] [type=T] [width=N]",
+        "Show a report; formats are text, json, csv, md and html",
+        &[Arg::Options],
+    ),
+    command(
+        "export",
+        "export [format] [path]",
+        "Write a report file, html by default",
+        &[Arg::Format, Arg::Text],
+    ),
+    command(
+        "diff",
+        "diff <path> [threshold=N] [json]",
+        "Compare with a saved JSON report or house file",
+        &[Arg::Text, Arg::Options],
+    ),
+    command(
+        "history",
+        "history <room>/<device> [minutes=N] [bucket=S]",
+        "Show recorded readings, optionally in buckets of S seconds",
+        &[Arg::Device, Arg::Options],
+    ),
+    command(
+        "energy",
+        "energy [from=YYYY-MM-DD] [to=YYYY-MM-DD] [tariff=T] [format=F]",
+        "Show energy and cost of power sockets; tariff is 0.25 or 23-7:0.10,0.30",
+        &[Arg::Options],
+    ),
+    command("save", "save [path]", "Save the house", &[Arg::Text]),
+    command("load", "load [path]", "Load a saved house", &[Arg::Text]),
+    command(
+        "exit",
+        "exit",
+        "Leave the program, same as 'quit' or Ctrl-D",
+        &[],
+    ),
+];
+
+pub fn find_command(name: &str) -> Option<&'static CommandSpec> {
+    let name = if name == "quit" { "exit" } else { name };
+    COMMANDS.iter().find(|command| command.name == name)
+}
+
+struct Word {
+    start: usize,
+    text: String,
+}
+
+/// Splits `line` into words, unquoting them; returns the quote left open at the end, if any.
+fn scan(line: &str) -> (Vec<Word>, Option<char>) {
+    let mut words: Vec<Word> = Vec::new();
+    let mut current: Option<Word> = None;
+    let mut quote = None;
+    let mut chars = line.char_indices();
+    while let Some((i, c)) = chars.next() {
+        let word = current.get_or_insert_with(|| Word {
+            start: i,
+            text: String::new(),
+        });
+        match (quote, c) {
+            (_, '\\') if quote != Some('\'') => {
+                if let Some((_, escaped)) = chars.next() {
+                    word.text.push(escaped);
+                }
+            }
+            (Some(open), c) if c == open => quote = None,
+            (Some(_), c) => word.text.push(c),
+            (None, '"' | '\'') => quote = Some(c),
+            (None, c) if c.is_whitespace() => {
+                if word.start < i {
+                    words.push(current.take().unwrap());
+                } else {
+                    current = None;
+                }
+            }
+            (None, c) => word.text.push(c),
+        }
+    }
+    words.extend(current);
+    (words, quote)
+}
+
+/// Splits a command line into words. Quotes group words, a backslash escapes the next character.
+pub fn split_words(line: &str) -> Result<Vec<String>, SmartHouseError> {
+    match scan(line) {
+        (_, Some(quote)) => Err(SmartHouseError::InvalidParameter(format!(
+            "unclosed {} quote",
+            quote
+        ))),
+        (words, None) => Ok(words.into_iter().map(|word| word.text).collect()),
+    }
+}
+
+/// Splits `room/device` at the first slash.
+pub fn split_device(word: &str) -> Option<(&str, &str)> {
+    word.split_once('/')
+        .filter(|(room, device)| !room.is_empty() && !device.is_empty())
+}
+
+/// Quotes a name when it would otherwise not survive [`split_words`]; names never hold a `/`.
+pub fn quote(name: &str) -> String {
+    match name.contains(|c: char| c.is_whitespace() || "\"'\\".contains(c)) {
+        true => format!("\"{}\"", name.replace('\\', "\\\\").replace('"', "\\\"")),
+        false => name.to_string(),
+    }
+}
+
+/// Completes command names and the names of rooms, devices, groups and scenes.
+#[derive(Default)]
+pub struct ReplHelper {
+    rooms: Vec<(String, Vec<String>)>,
+    groups: Vec<String>,
+    scenes: Vec<String>,
+}
+
+impl ReplHelper {
+    /// Takes the names to complete from the current house.
+    pub fn refresh(&mut self, house: &House) {
+        self.rooms = house
+            .rooms()
+            .map(|(name, room)| (name.to_string(), room.get_devices_names()))
+            .collect();
+        self.groups = house.get_groups_names();
+        self.scenes = house.get_scenes_names();
+    }
+
+    fn candidates(&self, arg: Arg, prefix: &str) -> Vec<String> {
+        let matching = |names: &[String]| -> Vec<String> {
+            names
+                .iter()
+                .filter(|name| name.starts_with(prefix))
+                .map(|name| quote(name))
+                .collect()
+        };
+        match arg {
+            Arg::Room => {
+                let rooms: Vec<String> = self.rooms.iter().map(|(room, _)| room.clone()).collect();
+                matching(&rooms)
+            }
+            Arg::Device => match prefix.split_once('/') {
+                Some((room, device)) => self
+                    .rooms
+                    .iter()
+                    .filter(|(name, _)| name == room)
+                    .flat_map(|(_, devices)| devices.iter())
+                    .filter(|name| name.starts_with(device))
+                    .map(|name| format!("{}/{}", quote(room), quote(name)))
+                    .collect(),
+                None => self
+                    .rooms
+                    .iter()
+                    .filter(|(room, _)| room.starts_with(prefix))
+                    .map(|(room, _)| format!("{}/", quote(room)))
+                    .collect(),
+            },
+            Arg::Group => matching(&self.groups),
+            Arg::Scene => matching(&self.scenes),
+            Arg::Type => {
+                let types: Vec<String> = DeviceType::ALL
+                    .iter()
+                    .map(|kind| format!("{:?}", kind))
+                    .collect();
+                matching(&types)
+            }
+            Arg::Format => {
+                let formats = ["text", "json", "csv", "md", "html"].map(str::to_string);
+                matching(&formats)
+            }
+            Arg::Text | Arg::Secret | Arg::Options => Vec::new(),
+        }
+    }
+
+    /// Completion candidates for the word under `pos` and where that word starts.
+    fn complete_at(&self, line: &str, pos: usize) -> (usize, Vec<String>) {
+        let line = &line[..pos];
+        let (mut words, quote) = scan(line);
+        let ends_word = quote.is_none() && line.ends_with(char::is_whitespace);
+        if words.is_empty() || ends_word {
+            words.push(Word {
+                start: pos,
+                text: String::new(),
+            });
+        }
+        let index = words.len() - 1;
+        let word = &words[index];
+        let candidates = match index {
+            0 => COMMANDS
+                .iter()
+                .filter(|command| command.name.starts_with(&word.text))
+                .map(|command| command.name.to_string())
+                .collect(),
+            _ => find_command(&words[0].text)
+                .and_then(|command| command.args.get(index - 1))
+                .map(|arg| self.candidates(*arg, &word.text))
+                .unwrap_or_default(),
+        };
+        (word.start, candidates)
+    }
+}
+
+impl Completer for ReplHelper {
+    type Candidate = Pair;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let (start, candidates) = self.complete_at(line, pos);
+        let pairs = candidates
+            .into_iter()
+            .map(|candidate| Pair {
+                display: candidate.clone(),
+                replacement: candidate,
+            })
+            .collect();
+        Ok((start, pairs))
+    }
+}
+
+impl Hinter for ReplHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ReplHelper {}
+
+impl Validator for ReplHelper {}
+
+impl Helper for ReplHelper {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use smart_house_lib::smart_device::SmartDevice;
+
+    #[test]
+    fn test_split_words() {
+        assert_eq!(
+            split_words(r#"on "Second room"/Lamp_1"#).unwrap(),
+            vec!["on", "Second room/Lamp_1"]
+        );
+        assert_eq!(
+            split_words(r#"  rename 'a b'/c  d\ e "" "#).unwrap(),
+            vec!["rename", "a b/c", "d e", ""]
+        );
+        assert!(split_words("on \"Hall").is_err());
+        assert_eq!(split_device("Hall/Lamp"), Some(("Hall", "Lamp")));
+        assert_eq!(split_device("Hall/"), None);
+    }
+
+    #[test]
+    fn test_completion() {
+        let mut house = House::new();
+        house.add_room("Second room", None).unwrap();
+        house.add_room("Hall", None).unwrap();
+        house
+            .get_mut_room("Second room")
+            .unwrap()
+            .add_device("Lamp_1", SmartDevice::power_socket(60.0))
+            .unwrap();
+        let mut helper = ReplHelper::default();
+        helper.refresh(&house);
+
+        assert_eq!(helper.complete_at("of", 2), (0, vec!["off".to_string()]));
+        assert_eq!(
+            helper.complete_at("on Se", 5),
+            (3, vec!["\"Second room\"/".to_string()])
+        );
+        let line = "on \"Second room\"/L";
+        assert_eq!(
+            helper.complete_at(line, line.len()),
+            (3, vec!["\"Second room\"/Lamp_1".to_string()])
+        );
+        assert_eq!(
+            helper.complete_at("move Hall/Lamp ", 15),
+            (15, vec!["\"Second room\"".to_string(), "Hall".to_string()])
+        );
+    }
+}
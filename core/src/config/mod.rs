@@ -0,0 +1,687 @@
+use crate::builder::HouseBuilder;
+use crate::error::SmartHouseError;
+use crate::house::House;
+use crate::labels::Labels;
+use crate::room::Room;
+use crate::scene::Scene;
+use crate::smart_device::{DeviceType, LockAuditEntry, PinCode, SmartDevice};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::ops::Range;
+use std::path::Path;
+use toml::Spanned;
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct HouseConfig {
+    pub rooms: Vec<RoomConfig>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub alarm_shutoffs: Vec<DeviceRef>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub groups: Vec<GroupConfig>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub scenes: Vec<Scene>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RoomConfig {
+    pub name: String,
+    #[serde(default)]
+    pub devices: Vec<DeviceConfig>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub metadata: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeviceConfig {
+    pub name: String,
+    pub kind: DeviceType,
+    #[serde(default)]
+    pub value: f64,
+    #[serde(default)]
+    pub on: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub codes: Vec<PinCode>,
+    /// Position a blind is moving to; `value` holds where it is now.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub speed: Option<f64>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub jammed: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub audit: Vec<LockAuditEntry>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub metadata: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GroupConfig {
+    pub name: String,
+    #[serde(default)]
+    pub members: Vec<DeviceRef>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeviceRef {
+    pub room: String,
+    pub device: String,
+}
+
+impl DeviceConfig {
+    pub fn from_device(name: &str, device: &SmartDevice) -> Self {
+        DeviceConfig {
+            name: name.to_string(),
+            kind: device.device_type(),
+            value: device.get_parameter(),
+            on: device.is_on(),
+            codes: device.pin_codes(),
+            target: device.is_blind().then(|| device.target_position()),
+            speed: device.is_blind().then(|| device.speed()),
+            jammed: device.is_jammed(),
+            audit: device.audit_trail(),
+            tags: device.labels().tags().map(str::to_string).collect(),
+            metadata: metadata_of(device.labels()),
+        }
+    }
+
+    /// Creates a fresh device through the devices ABI and restores its state.
+    pub fn try_create_device(&self) -> Result<SmartDevice, SmartHouseError> {
+        let mut device = match self.kind {
+            DeviceType::PowerSocket => SmartDevice::power_socket(self.value),
+            DeviceType::Thermometer => SmartDevice::try_thermometer(self.value)?,
+            DeviceType::DoorLock => SmartDevice::door_lock(),
+            DeviceType::Blind => SmartDevice::blind(self.value),
+            DeviceType::SmokeDetector => SmartDevice::smoke_detector(),
+        };
+        for code in &self.codes {
+            device.add_code(&code.user, &code.pin);
+        }
+        for entry in &self.audit {
+            device.push_audit_entry(entry);
+        }
+        apply_labels(device.labels_mut(), &self.tags, &self.metadata);
+        match (self.kind, self.on) {
+            // A blind's state is its position, which `value` already restored.
+            (DeviceType::Blind, _) => {}
+            (_, true) => device.turn_on(),
+            (_, false) => device.turn_off(),
+        }
+        if let Some(target) = self.target {
+            device.set_position(target);
+        }
+        if let Some(speed) = self.speed {
+            device.set_speed(speed);
+        }
+        if self.jammed {
+            device.set_jammed(true);
+        }
+        Ok(device)
+    }
+}
+
+fn metadata_of(labels: &Labels) -> BTreeMap<String, String> {
+    labels
+        .metadata()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+}
+
+fn apply_labels(labels: &mut Labels, tags: &[String], metadata: &BTreeMap<String, String>) {
+    for tag in tags {
+        labels.add_tag(tag);
+    }
+    for (key, value) in metadata {
+        labels.set_meta(key, value);
+    }
+}
+
+impl House {
+    pub fn to_config(&self) -> HouseConfig {
+        let rooms = self
+            .rooms()
+            .map(|(room_name, room)| RoomConfig {
+                name: room_name.to_string(),
+                devices: room
+                    .devices()
+                    .map(|(name, device)| DeviceConfig::from_device(name, device))
+                    .collect(),
+                tags: room.labels().tags().map(str::to_string).collect(),
+                metadata: metadata_of(room.labels()),
+            })
+            .collect();
+        let alarm_shutoffs = self
+            .get_alarm_shutoffs()
+            .iter()
+            .map(|(room, device)| DeviceRef {
+                room: room.clone(),
+                device: device.clone(),
+            })
+            .collect();
+        let groups = self
+            .get_groups_names()
+            .into_iter()
+            .map(|name| GroupConfig {
+                members: self
+                    .get_group(&name)
+                    .unwrap_or_default()
+                    .iter()
+                    .map(|(room, device)| DeviceRef {
+                        room: room.clone(),
+                        device: device.clone(),
+                    })
+                    .collect(),
+                name,
+            })
+            .collect();
+        let scenes = self
+            .get_scenes_names()
+            .iter()
+            .filter_map(|name| self.get_scene(name).cloned())
+            .collect();
+        HouseConfig {
+            rooms,
+            alarm_shutoffs,
+            groups,
+            scenes,
+        }
+    }
+
+    pub fn from_config(config: &HouseConfig) -> Result<House, SmartHouseError> {
+        House::from_config_with_warnings(config).map(|(house, _)| house)
+    }
+
+    /// Same as [`House::from_config`], also returning the references to missing
+    /// devices that were skipped.
+    pub fn from_config_with_warnings(
+        config: &HouseConfig,
+    ) -> Result<(House, Vec<String>), SmartHouseError> {
+        let mut house = House::new();
+        let mut warnings = Vec::new();
+        for room_config in &config.rooms {
+            let mut room = Room::new();
+            apply_labels(room.labels_mut(), &room_config.tags, &room_config.metadata);
+            for device_config in &room_config.devices {
+                room.add_device(&device_config.name, device_config.try_create_device()?)?;
+            }
+            house.add_room(&room_config.name, Some(room))?;
+        }
+        for shutoff in &config.alarm_shutoffs {
+            if let Err(err) = house.add_alarm_shutoff(&shutoff.room, &shutoff.device) {
+                warnings.push(format!(
+                    "alarm shutoff '{}/{}' skipped: {}",
+                    shutoff.room, shutoff.device, err
+                ));
+            }
+        }
+        for group in &config.groups {
+            house.create_group(&group.name)?;
+            for member in &group.members {
+                if let Err(err) = house.add_to_group(&group.name, &member.room, &member.device) {
+                    warnings.push(format!(
+                        "group '{}' member '{}/{}' skipped: {}",
+                        group.name, member.room, member.device, err
+                    ));
+                }
+            }
+        }
+        for scene in &config.scenes {
+            house.add_scene(scene.clone());
+        }
+        Ok((house, warnings))
+    }
+
+    pub fn to_json(&self) -> Result<String, SmartHouseError> {
+        serde_json::to_string_pretty(&self.to_config())
+            .map_err(|err| SmartHouseError::Storage(err.to_string()))
+    }
+
+    pub fn from_json(json: &str) -> Result<House, SmartHouseError> {
+        House::from_json_with_warnings(json).map(|(house, _)| house)
+    }
+
+    pub fn from_json_with_warnings(json: &str) -> Result<(House, Vec<String>), SmartHouseError> {
+        let config: HouseConfig =
+            serde_json::from_str(json).map_err(|err| SmartHouseError::Storage(err.to_string()))?;
+        House::from_config_with_warnings(&config)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), SmartHouseError> {
+        fs::write(path, self.to_json()?).map_err(|err| SmartHouseError::Storage(err.to_string()))
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<House, SmartHouseError> {
+        House::load_with_warnings(path).map(|(house, _)| house)
+    }
+
+    pub fn load_with_warnings(
+        path: impl AsRef<Path>,
+    ) -> Result<(House, Vec<String>), SmartHouseError> {
+        let json =
+            fs::read_to_string(path).map_err(|err| SmartHouseError::Storage(err.to_string()))?;
+        House::from_json_with_warnings(&json)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TomlHouse {
+    #[serde(default)]
+    rooms: Vec<TomlRoom>,
+    #[serde(default)]
+    alarm_shutoffs: Vec<TomlDeviceRef>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TomlRoom {
+    name: Spanned<String>,
+    #[serde(default)]
+    devices: Vec<TomlDevice>,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    metadata: BTreeMap<String, String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TomlDevice {
+    name: Spanned<String>,
+    kind: DeviceType,
+    value: Option<Spanned<f64>>,
+    #[serde(default)]
+    on: bool,
+    #[serde(default)]
+    codes: Vec<Spanned<PinCode>>,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    metadata: BTreeMap<String, String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TomlDeviceRef {
+    room: Spanned<String>,
+    device: Spanned<String>,
+}
+
+/// Validates a TOML house description, keeping track of where each value came from.
+struct TomlValidator<'a> {
+    source: &'a str,
+}
+
+impl TomlValidator<'_> {
+    fn error(&self, span: Range<usize>, key: String, message: &str) -> SmartHouseError {
+        SmartHouseError::Config {
+            line: self.source[..span.start.min(self.source.len())]
+                .matches('\n')
+                .count()
+                + 1,
+            key,
+            message: message.to_string(),
+        }
+    }
+
+    fn room(&self, index: usize, room: &TomlRoom) -> Result<(), SmartHouseError> {
+        let key = format!("rooms[{}]", index);
+        if room.name.get_ref().trim().is_empty() {
+            return Err(self.error(room.name.span(), key + ".name", "name is empty"));
+        }
+        if room.name.get_ref().contains('/') {
+            return Err(self.error(room.name.span(), key + ".name", "name contains '/'"));
+        }
+        let mut names = HashSet::new();
+        for (device_index, device) in room.devices.iter().enumerate() {
+            let key = format!("{}.devices[{}]", key, device_index);
+            if device.name.get_ref().trim().is_empty() {
+                return Err(self.error(device.name.span(), key + ".name", "name is empty"));
+            }
+            if device.name.get_ref().contains('/') {
+                return Err(self.error(device.name.span(), key + ".name", "name contains '/'"));
+            }
+            if !names.insert(device.name.get_ref().as_str()) {
+                return Err(self.error(
+                    device.name.span(),
+                    key + ".name",
+                    "device name is already used in this room",
+                ));
+            }
+            self.device(&key, device)?;
+        }
+        Ok(())
+    }
+
+    fn device(&self, key: &str, device: &TomlDevice) -> Result<(), SmartHouseError> {
+        if let Some(value) = &device.value {
+            let valid = match device.kind {
+                DeviceType::PowerSocket => *value.get_ref() >= 0.0,
+                DeviceType::Blind => (0.0..=100.0).contains(value.get_ref()),
+                _ => value.get_ref().is_finite(),
+            };
+            if !valid {
+                return Err(self.error(
+                    value.span(),
+                    format!("{}.value", key),
+                    "value is out of range for this device kind",
+                ));
+            }
+        }
+        if !device.codes.is_empty() && device.kind != DeviceType::DoorLock {
+            return Err(self.error(
+                device.codes[0].span(),
+                format!("{}.codes", key),
+                "only door locks accept PIN codes",
+            ));
+        }
+        let mut pins = HashSet::new();
+        for (index, code) in device.codes.iter().enumerate() {
+            let PinCode { user, pin } = code.get_ref();
+            if user.is_empty() || pin.is_empty() || !pins.insert(pin.as_str()) {
+                return Err(self.error(
+                    code.span(),
+                    format!("{}.codes[{}]", key, index),
+                    "PIN code needs a user and a unique, non-empty pin",
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl HouseBuilder {
+    /// Prepares a builder from a TOML house description.
+    ///
+    /// Syntax and validation errors are reported as [`SmartHouseError::Config`]
+    /// with the line and key of the offending value.
+    pub fn from_toml(source: &str) -> Result<HouseBuilder, SmartHouseError> {
+        let house: TomlHouse = toml::from_str(source).map_err(|err| {
+            let validator = TomlValidator { source };
+            validator.error(err.span().unwrap_or(0..0), String::new(), err.message())
+        })?;
+
+        let validator = TomlValidator { source };
+        let mut room_names = HashSet::new();
+        for (index, room) in house.rooms.iter().enumerate() {
+            if !room_names.insert(room.name.get_ref().as_str()) {
+                return Err(validator.error(
+                    room.name.span(),
+                    format!("rooms[{}].name", index),
+                    "room name is already used",
+                ));
+            }
+            validator.room(index, room)?;
+        }
+        for (index, shutoff) in house.alarm_shutoffs.iter().enumerate() {
+            let room = house
+                .rooms
+                .iter()
+                .find(|room| room.name.get_ref() == shutoff.room.get_ref());
+            let Some(room) = room else {
+                return Err(validator.error(
+                    shutoff.room.span(),
+                    format!("alarm_shutoffs[{}].room", index),
+                    "unknown room",
+                ));
+            };
+            if !room
+                .devices
+                .iter()
+                .any(|device| device.name.get_ref() == shutoff.device.get_ref())
+            {
+                return Err(validator.error(
+                    shutoff.device.span(),
+                    format!("alarm_shutoffs[{}].device", index),
+                    "unknown device",
+                ));
+            }
+        }
+
+        let mut builder = HouseBuilder::new();
+        for room in &house.rooms {
+            let mut room_builder = builder.add_room(room.name.get_ref());
+            for tag in &room.tags {
+                room_builder = room_builder.add_tag(tag);
+            }
+            for (key, value) in &room.metadata {
+                room_builder = room_builder.set_meta(key, value);
+            }
+            for device in &room.devices {
+                let config = DeviceConfig {
+                    name: device.name.get_ref().clone(),
+                    kind: device.kind,
+                    value: device.value.as_ref().map_or(0.0, |v| *v.get_ref()),
+                    on: device.on,
+                    codes: device.codes.iter().map(|c| c.get_ref().clone()).collect(),
+                    target: None,
+                    speed: None,
+                    jammed: false,
+                    audit: Vec::new(),
+                    tags: device.tags.clone(),
+                    metadata: device.metadata.clone(),
+                };
+                room_builder =
+                    room_builder.try_add_device(&config.name, config.try_create_device());
+            }
+            builder = room_builder.build();
+        }
+        for shutoff in &house.alarm_shutoffs {
+            builder = builder.add_alarm_shutoff(shutoff.room.get_ref(), shutoff.device.get_ref());
+        }
+        Ok(builder)
+    }
+
+    pub fn from_toml_file(path: impl AsRef<Path>) -> Result<HouseBuilder, SmartHouseError> {
+        let source =
+            fs::read_to_string(path).map_err(|err| SmartHouseError::Storage(err.to_string()))?;
+        HouseBuilder::from_toml(&source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_house() -> House {
+        let mut house = House::new();
+        house.add_room("Kitchen", None).unwrap();
+        house.add_room("Hall", None).unwrap();
+        let kitchen = house.get_mut_room("Kitchen").unwrap();
+        kitchen
+            .add_device("Kettle", SmartDevice::power_socket(2000.0))
+            .unwrap();
+        kitchen
+            .add_device("Detector", SmartDevice::smoke_detector())
+            .unwrap();
+        kitchen.get_mut_device("Kettle").unwrap().turn_on();
+        let hall = house.get_mut_room("Hall").unwrap();
+        hall.add_device("Door", SmartDevice::door_lock()).unwrap();
+        hall.add_device("Blind", SmartDevice::blind(40.0)).unwrap();
+        hall.add_device("Lamp", SmartDevice::power_socket(60.0))
+            .unwrap();
+        hall.labels_mut().add_tag("downstairs");
+        let door = hall.get_mut_device("Door").unwrap();
+        door.add_code("alice", "1234");
+        door.unlock("1234");
+        door.lock();
+        door.labels_mut().set_meta("vendor", "Acme");
+        let blind = hall.get_mut_device("Blind").unwrap();
+        blind.set_position(90.0);
+        blind.set_speed(5.0);
+        house.add_alarm_shutoff("Kitchen", "Kettle").unwrap();
+        house.create_group("Sockets").unwrap();
+        house.add_to_group("Sockets", "Kitchen", "Kettle").unwrap();
+        house.add_to_group("Sockets", "Hall", "Lamp").unwrap();
+        let sockets = house.get_group("Sockets").unwrap().to_vec();
+        house.capture_scene("Night", &sockets).unwrap();
+        house
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let house = sample_house();
+        let json = house.to_json().unwrap();
+        let restored = House::from_json(&json).unwrap();
+
+        assert_eq!(restored.to_config(), house.to_config());
+        assert_eq!(restored.get_rooms_names(), vec!["Kitchen", "Hall"]);
+        let kettle = restored.get_device("Kitchen", "Kettle").unwrap();
+        assert_eq!(kettle.get_value(), 2000.0);
+        let lamp = restored.get_device("Hall", "Lamp").unwrap();
+        assert!(!lamp.is_on());
+        assert_eq!(lamp.get_parameter(), 60.0);
+        let blind = restored.get_device("Hall", "Blind").unwrap();
+        assert_eq!(blind.get_value(), 40.0);
+        assert_eq!(blind.target_position(), 90.0);
+        assert_eq!(blind.speed(), 5.0);
+        let door = restored.get_device("Hall", "Door").unwrap();
+        assert_eq!(door.audit_trail()[0].user, "alice");
+
+        let mut house = sample_house();
+        let door = house.get_mut_room("Hall").unwrap().get_mut_device("Door");
+        door.unwrap().set_jammed(true);
+        let restored = House::from_json(&house.to_json().unwrap()).unwrap();
+        assert!(restored.get_device("Hall", "Door").unwrap().is_jammed());
+    }
+
+    #[test]
+    fn test_restored_lock_keeps_codes() {
+        let json = sample_house().to_json().unwrap();
+        let mut restored = House::from_json(&json).unwrap();
+        let door = restored
+            .get_mut_room("Hall")
+            .unwrap()
+            .get_mut_device("Door")
+            .unwrap();
+        assert!(door.is_on());
+        assert!(door.unlock("1234"));
+    }
+
+    #[test]
+    fn test_deleted_group_member_round_trip() {
+        let mut house = sample_house();
+        house.del_device("Hall", "Lamp").unwrap();
+        let restored = House::from_json(&house.to_json().unwrap()).unwrap();
+        assert_eq!(
+            restored.get_group("Sockets").unwrap(),
+            &[("Kitchen".to_string(), "Kettle".to_string())]
+        );
+
+        let mut house = sample_house();
+        house.del_room("Kitchen").unwrap();
+        let restored = House::from_json(&house.to_json().unwrap()).unwrap();
+        assert!(restored.get_alarm_shutoffs().is_empty());
+
+        let mut config = sample_house().to_config();
+        config.groups[0].members.push(DeviceRef {
+            room: "Attic".to_string(),
+            device: "Fan".to_string(),
+        });
+        config.alarm_shutoffs[0].device = "Oven".to_string();
+        let (restored, warnings) = House::from_config_with_warnings(&config).unwrap();
+        assert_eq!(restored.get_group("Sockets").unwrap().len(), 2);
+        assert!(restored.get_alarm_shutoffs().is_empty());
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings[1].contains("'Attic/Fan'"));
+    }
+
+    const TOML_HOUSE: &str = r#"
+[[rooms]]
+name = "Kitchen"
+
+[[rooms.devices]]
+name = "Kettle"
+kind = "PowerSocket"
+value = 2000.0
+on = true
+
+[[rooms.devices]]
+name = "Detector"
+kind = "SmokeDetector"
+
+[[rooms]]
+name = "Hall"
+tags = ["downstairs"]
+
+[[rooms.devices]]
+name = "Door"
+kind = "DoorLock"
+on = true
+codes = [{ user = "alice", pin = "1234" }]
+metadata = { vendor = "Acme" }
+
+[[alarm_shutoffs]]
+room = "Kitchen"
+device = "Kettle"
+"#;
+
+    #[test]
+    fn test_builder_from_toml() {
+        let house = HouseBuilder::from_toml(TOML_HOUSE)
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(house.get_rooms_names(), vec!["Kitchen", "Hall"]);
+        let kettle = house.get_device("Kitchen", "Kettle").unwrap();
+        assert_eq!(kettle.get_value(), 2000.0);
+        let door = house.get_device("Hall", "Door").unwrap();
+        assert!(door.is_on());
+        assert_eq!(door.labels().get_meta("vendor"), Some("Acme"));
+        assert!(
+            house
+                .get_room("Hall")
+                .unwrap()
+                .labels()
+                .has_tag("downstairs")
+        );
+        assert_eq!(house.get_alarm_shutoffs().len(), 1);
+    }
+
+    #[test]
+    fn test_toml_validation_points_at_key() {
+        let source = TOML_HOUSE.replace("value = 2000.0", "value = -5.0");
+        let err = HouseBuilder::from_toml(&source).err().unwrap();
+        assert_eq!(
+            err,
+            SmartHouseError::Config {
+                line: 8,
+                key: "rooms[0].devices[0].value".to_string(),
+                message: "value is out of range for this device kind".to_string(),
+            }
+        );
+
+        let source = TOML_HOUSE.replace("name = \"Detector\"", "name = \"Kettle\"");
+        let err = HouseBuilder::from_toml(&source).err().unwrap();
+        assert!(matches!(
+            err,
+            SmartHouseError::Config { line: 12, ref key, .. } if key == "rooms[0].devices[1].name"
+        ));
+
+        let source = TOML_HOUSE.replace("name = \"Detector\"", "name = \"Smoke/CO\"");
+        let err = HouseBuilder::from_toml(&source).err().unwrap();
+        assert!(matches!(
+            err,
+            SmartHouseError::Config { line: 12, ref message, .. } if message == "name contains '/'"
+        ));
+    }
+
+    #[test]
+    fn test_toml_syntax_error_has_line() {
+        let source = TOML_HOUSE.replace("kind = \"DoorLock\"", "kind = \"Toaster\"");
+        let err = HouseBuilder::from_toml(&source).err().unwrap();
+        assert!(matches!(err, SmartHouseError::Config { line: 21, .. }));
+    }
+
+    #[test]
+    fn test_from_json_rejects_invalid_input() {
+        assert!(matches!(
+            House::from_json("{\"rooms\": 5}"),
+            Err(SmartHouseError::Storage(_))
+        ));
+    }
+}
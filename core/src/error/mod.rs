@@ -0,0 +1,54 @@
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SmartHouseError {
+    RoomNotFound(String),
+    DeviceNotFound(String),
+    GroupNotFound(String),
+    SceneNotFound(String),
+    DuplicateName(String),
+    EmptyName(String),
+    InvalidParameter(String),
+    Unsupported(String),
+    Backend(String),
+    Storage(String),
+    Config {
+        line: usize,
+        key: String,
+        message: String,
+    },
+}
+
+impl Display for SmartHouseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            SmartHouseError::RoomNotFound(name) => write!(f, "Room not found: {}", name),
+            SmartHouseError::DeviceNotFound(name) => write!(f, "Device not found: {}", name),
+            SmartHouseError::GroupNotFound(name) => write!(f, "Group not found: {}", name),
+            SmartHouseError::SceneNotFound(name) => write!(f, "Scene not found: {}", name),
+            SmartHouseError::DuplicateName(name) => write!(f, "Name already in use: {}", name),
+            SmartHouseError::EmptyName(what) => write!(f, "Name must not be empty: {}", what),
+            SmartHouseError::InvalidParameter(message) => {
+                write!(f, "Invalid parameter: {}", message)
+            }
+            SmartHouseError::Unsupported(message) => {
+                write!(f, "Unsupported operation: {}", message)
+            }
+            SmartHouseError::Backend(message) => write!(f, "Device backend error: {}", message),
+            SmartHouseError::Storage(message) => write!(f, "Storage error: {}", message),
+            SmartHouseError::Config { line, key, message } if key.is_empty() => {
+                write!(f, "Config error at line {}: {}", line, message)
+            }
+            SmartHouseError::Config { line, key, message } => {
+                write!(
+                    f,
+                    "Config error at line {}, key '{}': {}",
+                    line, key, message
+                )
+            }
+        }
+    }
+}
+
+impl Error for SmartHouseError {}
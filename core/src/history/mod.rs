@@ -0,0 +1,371 @@
+use crate::clock::unix_now;
+use crate::error::SmartHouseError;
+use crate::house::House;
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+
+/// Device reading at a Unix time, in seconds.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Sample {
+    pub time: u64,
+    pub value: f64,
+    pub is_on: bool,
+}
+
+/// Min/avg/max of the samples falling into `[start, start + length)`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bucket {
+    pub start: u64,
+    pub min: f64,
+    pub avg: f64,
+    pub max: f64,
+    pub count: usize,
+}
+
+/// One line of the append-only history file.
+#[derive(Serialize, Deserialize)]
+struct Record {
+    room: String,
+    device: String,
+    #[serde(flatten)]
+    sample: Sample,
+}
+
+/// Readings of every device in a bounded buffer per device, optionally mirrored to disk.
+pub struct History {
+    capacity: usize,
+    interval: u64,
+    last_periodic: Option<u64>,
+    last_recorded: Option<u64>,
+    series: IndexMap<(String, String), VecDeque<Sample>>,
+    storage: Option<File>,
+}
+
+impl History {
+    /// Keeps up to `capacity` samples per device and samples all devices every `interval` seconds.
+    pub fn new(capacity: usize, interval: u64) -> Self {
+        History {
+            capacity: capacity.max(1),
+            interval,
+            last_periodic: None,
+            last_recorded: None,
+            series: IndexMap::new(),
+            storage: None,
+        }
+    }
+
+    /// Replays the samples stored at `path`, then keeps appending new ones there.
+    /// Lines that don't parse, such as one cut short by a crash, are skipped;
+    /// an unfinished last line is cut off so that new samples start on a line of their own.
+    pub fn open(
+        path: impl AsRef<Path>,
+        capacity: usize,
+        interval: u64,
+    ) -> Result<Self, SmartHouseError> {
+        let storage_error = |err: std::io::Error| SmartHouseError::Storage(err.to_string());
+        let mut history = History::new(capacity, interval);
+        let mut valid_len = None;
+        let mut unterminated = false;
+        if path.as_ref().exists() {
+            let file = File::open(&path).map_err(storage_error)?;
+            let mut reader = BufReader::new(file);
+            let mut offset = 0;
+            let mut line = Vec::new();
+            while reader.read_until(b'\n', &mut line).map_err(storage_error)? > 0 {
+                let parsed = serde_json::from_slice::<Record>(&line);
+                unterminated = !line.ends_with(b"\n");
+                match parsed {
+                    Ok(record) => history.push(record.room, record.device, record.sample),
+                    Err(_) if unterminated => valid_len = Some(offset),
+                    Err(_) => {}
+                }
+                offset += line.len() as u64;
+                line.clear();
+            }
+        }
+        if let Some(dir) = path
+            .as_ref()
+            .parent()
+            .filter(|dir| !dir.as_os_str().is_empty())
+        {
+            fs::create_dir_all(dir).map_err(storage_error)?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(storage_error)?;
+        match valid_len {
+            Some(len) => file.set_len(len).map_err(storage_error)?,
+            None if unterminated => file.write_all(b"\n").map_err(storage_error)?,
+            None => {}
+        }
+        history.storage = Some(file);
+        Ok(history)
+    }
+
+    /// Samples every device whose value or state changed since its last sample,
+    /// and every device once `interval` seconds passed since the last periodic round.
+    /// Returns the number of samples taken.
+    pub fn record(&mut self, house: &House, time: u64) -> Result<usize, SmartHouseError> {
+        let periodic = self
+            .last_periodic
+            .is_none_or(|last| time.saturating_sub(last) >= self.interval);
+        if periodic {
+            self.last_periodic = Some(time);
+        }
+        self.last_recorded = self.last_recorded.max(Some(time));
+
+        let mut taken = Vec::new();
+        for (room_name, device_name, device) in house.devices() {
+            let sample = Sample {
+                time,
+                value: device.get_value(),
+                is_on: device.is_on(),
+            };
+            let changed = self
+                .series
+                .get(&(room_name.to_string(), device_name.to_string()))
+                .and_then(|series| series.back())
+                .is_none_or(|last| last.value != sample.value || last.is_on != sample.is_on);
+            if periodic || changed {
+                taken.push((room_name.to_string(), device_name.to_string(), sample));
+            }
+        }
+
+        if let Some(file) = &mut self.storage {
+            let mut lines = String::new();
+            for (room, device, sample) in &taken {
+                let record = Record {
+                    room: room.clone(),
+                    device: device.clone(),
+                    sample: *sample,
+                };
+                let line = serde_json::to_string(&record)
+                    .map_err(|err| SmartHouseError::Storage(err.to_string()))?;
+                lines.push_str(&line);
+                lines.push('\n');
+            }
+            file.write_all(lines.as_bytes())
+                .map_err(|err| SmartHouseError::Storage(err.to_string()))?;
+        }
+        let count = taken.len();
+        for (room, device, sample) in taken {
+            self.push(room, device, sample);
+        }
+        Ok(count)
+    }
+
+    /// Same as [`History::record`] at the current system time.
+    pub fn record_now(&mut self, house: &House) -> Result<usize, SmartHouseError> {
+        self.record(house, unix_now())
+    }
+
+    fn push(&mut self, room: String, device: String, sample: Sample) {
+        self.last_recorded = self.last_recorded.max(Some(sample.time));
+        let series = self.series.entry((room, device)).or_default();
+        if series.len() == self.capacity {
+            series.pop_front();
+        }
+        series.push_back(sample);
+    }
+
+    /// Time of the latest recording round, whether or not it took any samples.
+    pub fn last_recorded(&self) -> Option<u64> {
+        self.last_recorded
+    }
+
+    /// Whether no samples of a device taken at or after `time` were dropped to make room.
+    pub fn covers(&self, room_name: &str, device_name: &str, time: u64) -> bool {
+        self.series
+            .get(&(room_name.to_string(), device_name.to_string()))
+            .filter(|series| series.len() == self.capacity)
+            .and_then(|series| series.front())
+            .is_none_or(|oldest| oldest.time <= time)
+    }
+
+    /// Devices with recorded samples, as `(room, device)`.
+    pub fn devices(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.series
+            .keys()
+            .map(|(room, device)| (room.as_str(), device.as_str()))
+    }
+
+    /// Samples of a device taken within `from..=to`, oldest first.
+    pub fn query(&self, room_name: &str, device_name: &str, from: u64, to: u64) -> Vec<Sample> {
+        self.series
+            .get(&(room_name.to_string(), device_name.to_string()))
+            .map(|series| {
+                series
+                    .iter()
+                    .filter(|sample| (from..=to).contains(&sample.time))
+                    .copied()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// The latest sample taken at or before `time`.
+    pub fn at(&self, room_name: &str, device_name: &str, time: u64) -> Option<Sample> {
+        self.series
+            .get(&(room_name.to_string(), device_name.to_string()))?
+            .iter()
+            .rev()
+            .find(|sample| sample.time <= time)
+            .copied()
+    }
+
+    /// Splits `from..=to` into buckets of `length` seconds; empty buckets are skipped.
+    pub fn downsample(
+        &self,
+        room_name: &str,
+        device_name: &str,
+        from: u64,
+        to: u64,
+        length: u64,
+    ) -> Vec<Bucket> {
+        let length = length.max(1);
+        let mut buckets: Vec<(Bucket, f64)> = Vec::new();
+        for sample in self.query(room_name, device_name, from, to) {
+            let start = from + (sample.time - from) / length * length;
+            match buckets.last_mut() {
+                Some((bucket, sum)) if bucket.start == start => {
+                    bucket.min = bucket.min.min(sample.value);
+                    bucket.max = bucket.max.max(sample.value);
+                    bucket.count += 1;
+                    *sum += sample.value;
+                }
+                _ => buckets.push((
+                    Bucket {
+                        start,
+                        min: sample.value,
+                        avg: 0.0,
+                        max: sample.value,
+                        count: 1,
+                    },
+                    sample.value,
+                )),
+            }
+        }
+        buckets
+            .into_iter()
+            .map(|(bucket, sum)| Bucket {
+                avg: sum / bucket.count as f64,
+                ..bucket
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::house;
+    use std::env;
+
+    #[test]
+    fn test_record_on_interval_and_change() {
+        let mut house =
+            house! { "Hall" { "Lamp": power_socket(60.0), "Heater": power_socket(2000.0) } };
+        let mut history = History::new(3, 60);
+        assert_eq!(history.record(&house, 1000).unwrap(), 2);
+        assert_eq!(history.record(&house, 1010).unwrap(), 0);
+
+        let hall = house.get_mut_room("Hall").unwrap();
+        hall.get_mut_device("Lamp").unwrap().turn_on();
+        assert_eq!(history.record(&house, 1020).unwrap(), 1);
+        assert_eq!(history.record(&house, 1060).unwrap(), 2);
+        assert_eq!(history.record(&house, 1120).unwrap(), 2);
+
+        let samples = history.query("Hall", "Lamp", 0, u64::MAX);
+        let times: Vec<u64> = samples.iter().map(|s| s.time).collect();
+        assert_eq!(times, vec![1020, 1060, 1120]);
+        assert!(samples.iter().all(|s| s.is_on && s.value == 60.0));
+        assert_eq!(history.query("Hall", "Lamp", 1030, 1100).len(), 1);
+    }
+
+    #[test]
+    fn test_downsample() {
+        let mut house = house! { "Hall" { "Lamp": power_socket(60.0) } };
+        let mut history = History::new(100, 1);
+        for (time, power) in [(0, 10.0), (5, 20.0), (12, 40.0), (30, 50.0)] {
+            let hall = house.get_mut_room("Hall").unwrap();
+            let lamp = hall.get_mut_device("Lamp").unwrap();
+            lamp.set_value(power);
+            lamp.turn_on();
+            history.record(&house, time).unwrap();
+        }
+        let buckets = history.downsample("Hall", "Lamp", 0, 29, 10);
+        assert_eq!(
+            buckets,
+            vec![
+                Bucket {
+                    start: 0,
+                    min: 10.0,
+                    avg: 15.0,
+                    max: 20.0,
+                    count: 2,
+                },
+                Bucket {
+                    start: 10,
+                    min: 40.0,
+                    avg: 40.0,
+                    max: 40.0,
+                    count: 1,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_disk_storage_round_trip() {
+        let path =
+            env::temp_dir().join(format!("smart_house_history_{}.jsonl", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let mut house =
+            house! { "Hall" { "Lamp": power_socket(60.0), "Heater": power_socket(2000.0) } };
+        {
+            let mut history = History::open(&path, 10, 60).unwrap();
+            history.record(&house, 100).unwrap();
+            let hall = house.get_mut_room("Hall").unwrap();
+            hall.get_mut_device("Lamp").unwrap().turn_on();
+            history.record(&house, 110).unwrap();
+        }
+        let history = History::open(&path, 10, 60).unwrap();
+        fs::remove_file(&path).unwrap();
+        let samples = history.query("Hall", "Lamp", 0, u64::MAX);
+        assert_eq!(samples.len(), 2);
+        assert!(samples[1].is_on);
+        assert_eq!(history.query("Hall", "Heater", 0, u64::MAX).len(), 1);
+    }
+
+    #[test]
+    fn test_open_skips_damaged_lines() {
+        let path = env::temp_dir().join(format!(
+            "smart_house_history_damaged_{}.jsonl",
+            std::process::id()
+        ));
+        let house = house! { "Hall" { "Lamp": power_socket(60.0) } };
+        {
+            let mut history = History::open(&path, 10, 60).unwrap();
+            history.record(&house, 100).unwrap();
+        }
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(b"garbage\n{\"room\":\"Hall\",\"dev")
+            .unwrap();
+        {
+            let mut history = History::open(&path, 10, 60).unwrap();
+            assert_eq!(history.query("Hall", "Lamp", 0, u64::MAX).len(), 1);
+            history.record(&house, 200).unwrap();
+        }
+        let history = History::open(&path, 10, 60).unwrap();
+        let contents = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(history.query("Hall", "Lamp", 0, u64::MAX).len(), 2);
+        assert!(contents.ends_with("}\n"));
+    }
+}
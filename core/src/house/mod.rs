@@ -1,23 +1,68 @@
+use crate::aggregate::Aggregates;
 use crate::builder::HouseBuilder;
-use crate::report::{Report, Reportable};
-use crate::room::Room;
+use crate::error::SmartHouseError;
+use crate::query::DeviceQuery;
+use crate::report::render::{Renderer, TextRenderer};
+use crate::report::{HouseReport, Report, ReportOptions, Reportable};
+use crate::room::{Room, check_name};
+use crate::scene::Scene;
 use crate::smart_device::SmartDevice;
-use std::collections::HashMap;
-use std::error::Error;
+use indexmap::IndexMap;
 
+/// Declares a house inline. Each comma separated entry is either `"Name" { ... }`,
+/// a nested room in [`crate::room!`] syntax, or `"Name": expr` with an already built room.
+/// Anything else fails to compile:
+///
+/// ```compile_fail
+/// smart_house_lib::house!("Hall" ["Lamp": power_socket(60.0)]);
+/// ```
 #[macro_export]
 macro_rules! house {
-    ( $( $key:tt : $room:expr ),* $(,)? ) => {{
-        let mut house = House::new();
-        $(
-            house.add_room($key, Option::from($room));
-        )*
+    (@rooms $house:ident;) => {};
+    (@rooms $house:ident; $name:tt { $($body:tt)* } $(, $($rest:tt)*)?) => {
+        $house
+            .add_room($name, Some($crate::room!($($body)*)))
+            .unwrap_or_else(|err| panic!("house!: {}", err));
+        $crate::house!(@rooms $house; $($($rest)*)?);
+    };
+    (@rooms $house:ident; $name:tt : $room:expr $(, $($rest:tt)*)?) => {
+        $house
+            .add_room($name, Option::from($room))
+            .unwrap_or_else(|err| panic!("house!: {}", err));
+        $crate::house!(@rooms $house; $($($rest)*)?);
+    };
+    (@rooms $house:ident; $($other:tt)*) => {
+        compile_error!(concat!("house!: malformed room: ", stringify!($($other)*)))
+    };
+
+    ( $($body:tt)* ) => {{
+        #[allow(unused_mut)]
+        let mut house = $crate::house::House::new();
+        $crate::house!(@rooms house; $($body)*);
         house
     }};
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlarmEvent {
+    pub sources: Vec<(String, String)>,
+    pub switched_off: Vec<(String, String)>,
+}
+
+/// Outcome of a bulk operation for one group member.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BulkOutcome {
+    pub room: String,
+    pub device: String,
+    pub result: Result<(), SmartHouseError>,
+}
+
 pub struct House {
-    rooms: HashMap<String, Room>,
+    rooms: IndexMap<String, Room>,
+    alarm_shutoffs: Vec<(String, String)>,
+    groups: IndexMap<String, Vec<(String, String)>>,
+    scenes: IndexMap<String, Scene>,
+    alarm: Option<AlarmEvent>,
 }
 
 impl Default for House {
@@ -30,6 +75,10 @@ impl House {
     pub fn new() -> Self {
         House {
             rooms: Default::default(),
+            alarm_shutoffs: Vec::new(),
+            groups: IndexMap::new(),
+            scenes: IndexMap::new(),
+            alarm: None,
         }
     }
 
@@ -45,53 +94,469 @@ impl House {
         self.rooms.get_mut(name)
     }
 
-    pub fn add_room(&mut self, name: &str, room: Option<Room>) {
+    pub fn add_room(&mut self, name: &str, room: Option<Room>) -> Result<(), SmartHouseError> {
+        check_name(name)?;
+        if self.rooms.contains_key(name) {
+            return Err(SmartHouseError::DuplicateName(name.to_string()));
+        }
         let room = room.unwrap_or_default();
         self.rooms.insert(name.to_string(), room);
+        Ok(())
+    }
+
+    /// Removes a room and drops its devices from groups and alarm shutoffs.
+    pub fn del_room(&mut self, name: &str) -> Result<Room, SmartHouseError> {
+        let room = self
+            .rooms
+            .shift_remove(name)
+            .ok_or_else(|| SmartHouseError::RoomNotFound(name.to_string()))?;
+        self.forget_devices(|room_name, _| room_name == name);
+        Ok(room)
+    }
+
+    /// Moves a room to `position` in the listing order, clamped to the last slot.
+    pub fn move_room(&mut self, name: &str, position: usize) -> Result<(), SmartHouseError> {
+        let from = self
+            .rooms
+            .get_index_of(name)
+            .ok_or_else(|| SmartHouseError::RoomNotFound(name.to_string()))?;
+        let to = position.min(self.rooms.len() - 1);
+        self.rooms.move_index(from, to);
+        Ok(())
+    }
+
+    /// Renames a room in place, keeping its position and devices.
+    pub fn rename_room(&mut self, name: &str, new_name: &str) -> Result<(), SmartHouseError> {
+        let index = self
+            .rooms
+            .get_index_of(name)
+            .ok_or_else(|| SmartHouseError::RoomNotFound(name.to_string()))?;
+        if new_name.trim().is_empty() {
+            return Err(SmartHouseError::EmptyName("room".to_string()));
+        }
+        if name == new_name {
+            return Ok(());
+        }
+        check_name(new_name)?;
+        if self.rooms.contains_key(new_name) {
+            return Err(SmartHouseError::DuplicateName(new_name.to_string()));
+        }
+        let (_, room) = self.rooms.shift_remove_index(index).unwrap();
+        let (new_index, _) = self.rooms.insert_full(new_name.to_string(), room);
+        self.rooms.move_index(new_index, index);
+        for (room_name, _) in self.device_refs_mut() {
+            if *room_name == name {
+                *room_name = new_name.to_string();
+            }
+        }
+        Ok(())
+    }
+
+    /// Adds a copy of `name` as `new_name`, with fresh devices created through the ABI.
+    pub fn duplicate_room(&mut self, name: &str, new_name: &str) -> Result<(), SmartHouseError> {
+        let room = self
+            .rooms
+            .get(name)
+            .ok_or_else(|| SmartHouseError::RoomNotFound(name.to_string()))?;
+        if new_name.trim().is_empty() {
+            return Err(SmartHouseError::EmptyName("room".to_string()));
+        }
+        if self.rooms.contains_key(new_name) {
+            return Err(SmartHouseError::DuplicateName(new_name.to_string()));
+        }
+        let copy = room.duplicate()?;
+        self.add_room(new_name, Some(copy))
+    }
+
+    /// Removes a device and drops it from groups and alarm shutoffs.
+    pub fn del_device(
+        &mut self,
+        room_name: &str,
+        device_name: &str,
+    ) -> Result<SmartDevice, SmartHouseError> {
+        let device = self
+            .rooms
+            .get_mut(room_name)
+            .ok_or_else(|| SmartHouseError::RoomNotFound(room_name.to_string()))?
+            .del_device(device_name)?;
+        self.forget_devices(|room, device| room == room_name && device == device_name);
+        Ok(device)
+    }
+
+    pub fn rename_device(
+        &mut self,
+        room_name: &str,
+        device_name: &str,
+        new_name: &str,
+    ) -> Result<(), SmartHouseError> {
+        self.rooms
+            .get_mut(room_name)
+            .ok_or_else(|| SmartHouseError::RoomNotFound(room_name.to_string()))?
+            .rename_device(device_name, new_name)?;
+        for (ref_room, ref_device) in self.device_refs_mut() {
+            if *ref_room == room_name && *ref_device == device_name {
+                *ref_device = new_name.to_string();
+            }
+        }
+        Ok(())
+    }
+
+    /// Moves a device to another room under the same name, keeping its state.
+    pub fn move_device(
+        &mut self,
+        from_room: &str,
+        device_name: &str,
+        to_room: &str,
+    ) -> Result<(), SmartHouseError> {
+        let target = self
+            .rooms
+            .get(to_room)
+            .ok_or_else(|| SmartHouseError::RoomNotFound(to_room.to_string()))?;
+        let source = self
+            .rooms
+            .get(from_room)
+            .ok_or_else(|| SmartHouseError::RoomNotFound(from_room.to_string()))?;
+        if source.get_device(device_name).is_none() {
+            return Err(SmartHouseError::DeviceNotFound(device_name.to_string()));
+        }
+        if from_room == to_room {
+            return Ok(());
+        }
+        if target.get_device(device_name).is_some() {
+            return Err(SmartHouseError::DuplicateName(device_name.to_string()));
+        }
+
+        let device = self.rooms[from_room].del_device(device_name)?;
+        self.rooms[to_room].add_device(device_name, device)?;
+        for (ref_room, ref_device) in self.device_refs_mut() {
+            if *ref_room == from_room && *ref_device == device_name {
+                *ref_room = to_room.to_string();
+            }
+        }
+        Ok(())
+    }
+
+    /// Drops group members and alarm shutoffs matching `(room, device)`; scenes keep
+    /// theirs and report them as missing when applied.
+    fn forget_devices(&mut self, matches: impl Fn(&str, &str) -> bool) {
+        self.alarm_shutoffs
+            .retain(|(room, device)| !matches(room, device));
+        for members in self.groups.values_mut() {
+            members.retain(|(room, device)| !matches(room, device));
+        }
     }
 
-    pub fn del_room(&mut self, name: &str) {
-        self.rooms.remove(name);
+    /// Alarm shutoffs and group members, which follow renamed and moved devices.
+    fn device_refs_mut(&mut self) -> impl Iterator<Item = (&mut String, &mut String)> {
+        let scene_refs = self.scenes.values_mut().flat_map(|scene| {
+            scene
+                .entries
+                .iter_mut()
+                .map(|entry| (&mut entry.room, &mut entry.device))
+        });
+        self.alarm_shutoffs
+            .iter_mut()
+            .map(|(room, device)| (room, device))
+            .chain(
+                self.groups
+                    .values_mut()
+                    .flatten()
+                    .map(|(room, device)| (room, device)),
+            )
+            .chain(scene_refs)
     }
 
     pub fn get_device(
         &self,
         room_name: &str,
         device_name: &str,
-    ) -> Result<&SmartDevice, Box<dyn Error>> {
+    ) -> Result<&SmartDevice, SmartHouseError> {
         let room = self
             .rooms
             .get(room_name)
-            .ok_or_else(|| format!("Room not found: {}", room_name))?;
+            .ok_or_else(|| SmartHouseError::RoomNotFound(room_name.to_string()))?;
 
         let device = room
             .get_device(device_name)
-            .ok_or_else(|| format!("Device not found: {}", device_name))?;
+            .ok_or_else(|| SmartHouseError::DeviceNotFound(device_name.to_string()))?;
 
         Ok(device)
     }
 
+    pub fn rooms(&self) -> impl Iterator<Item = (&str, &Room)> {
+        self.rooms.iter().map(|(name, room)| (name.as_str(), room))
+    }
+
+    /// Iterates over `(room, device name, device)` across all rooms.
+    pub fn devices(&self) -> impl Iterator<Item = (&str, &str, &SmartDevice)> {
+        self.rooms.iter().flat_map(|(room_name, room)| {
+            room.devices()
+                .map(move |(name, device)| (room_name.as_str(), name, device))
+        })
+    }
+
+    /// Totals over every device in the house.
+    pub fn aggregates(&self) -> Aggregates {
+        Aggregates::from_devices(self.devices().map(|(_, _, device)| device))
+    }
+
+    pub fn devices_mut(&mut self) -> impl Iterator<Item = (&str, &str, &mut SmartDevice)> {
+        self.rooms.iter_mut().flat_map(|(room_name, room)| {
+            room.devices_mut()
+                .map(move |(name, device)| (room_name.as_str(), name, device))
+        })
+    }
+
+    pub fn query<'a>(
+        &'a self,
+        query: &'a DeviceQuery,
+    ) -> impl Iterator<Item = (&'a str, &'a str, &'a SmartDevice)> {
+        self.rooms.iter().flat_map(move |(room_name, room)| {
+            room.devices()
+                .filter(move |(_, device)| query.matches(room_name, room, device))
+                .map(move |(name, device)| (room_name.as_str(), name, device))
+        })
+    }
+
+    pub fn query_mut<'a>(
+        &'a mut self,
+        query: &'a DeviceQuery,
+    ) -> impl Iterator<Item = (&'a str, &'a str, &'a mut SmartDevice)> {
+        let matches: Vec<bool> = self
+            .rooms
+            .iter()
+            .flat_map(|(room_name, room)| {
+                room.devices()
+                    .map(move |(_, device)| query.matches(room_name, room, device))
+            })
+            .collect();
+        self.devices_mut()
+            .zip(matches)
+            .filter_map(|(entry, matched)| matched.then_some(entry))
+    }
+
     pub fn get_rooms_names(&self) -> Vec<String> {
         self.rooms.keys().map(|k| k.to_string()).collect()
     }
+
+    /// Registers a device that is switched off whenever a detector raises the alarm.
+    pub fn add_alarm_shutoff(
+        &mut self,
+        room_name: &str,
+        device_name: &str,
+    ) -> Result<(), SmartHouseError> {
+        self.get_device(room_name, device_name)?;
+        let key = (room_name.to_string(), device_name.to_string());
+        if !self.alarm_shutoffs.contains(&key) {
+            self.alarm_shutoffs.push(key);
+        }
+        Ok(())
+    }
+
+    pub fn get_alarm_shutoffs(&self) -> &[(String, String)] {
+        &self.alarm_shutoffs
+    }
+
+    pub fn create_group(&mut self, name: &str) -> Result<(), SmartHouseError> {
+        if self.groups.contains_key(name) {
+            return Err(SmartHouseError::DuplicateName(name.to_string()));
+        }
+        self.groups.insert(name.to_string(), Vec::new());
+        Ok(())
+    }
+
+    pub fn del_group(&mut self, name: &str) -> Result<Vec<(String, String)>, SmartHouseError> {
+        self.groups
+            .shift_remove(name)
+            .ok_or_else(|| SmartHouseError::GroupNotFound(name.to_string()))
+    }
+
+    pub fn add_to_group(
+        &mut self,
+        group_name: &str,
+        room_name: &str,
+        device_name: &str,
+    ) -> Result<(), SmartHouseError> {
+        self.get_device(room_name, device_name)?;
+        let members = self
+            .groups
+            .get_mut(group_name)
+            .ok_or_else(|| SmartHouseError::GroupNotFound(group_name.to_string()))?;
+        let key = (room_name.to_string(), device_name.to_string());
+        if !members.contains(&key) {
+            members.push(key);
+        }
+        Ok(())
+    }
+
+    pub fn remove_from_group(
+        &mut self,
+        group_name: &str,
+        room_name: &str,
+        device_name: &str,
+    ) -> Result<(), SmartHouseError> {
+        let members = self
+            .groups
+            .get_mut(group_name)
+            .ok_or_else(|| SmartHouseError::GroupNotFound(group_name.to_string()))?;
+        let count = members.len();
+        members.retain(|(room, device)| room != room_name || device != device_name);
+        if members.len() == count {
+            return Err(SmartHouseError::DeviceNotFound(device_name.to_string()));
+        }
+        Ok(())
+    }
+
+    pub fn get_group(&self, name: &str) -> Option<&[(String, String)]> {
+        self.groups.get(name).map(Vec::as_slice)
+    }
+
+    pub fn get_groups_names(&self) -> Vec<String> {
+        self.groups.keys().cloned().collect()
+    }
+
+    pub fn group_turn_on(&mut self, name: &str) -> Result<Vec<BulkOutcome>, SmartHouseError> {
+        self.for_each_in_group(name, |device| device.switch(true))
+    }
+
+    pub fn group_turn_off(&mut self, name: &str) -> Result<Vec<BulkOutcome>, SmartHouseError> {
+        self.for_each_in_group(name, |device| device.switch(false))
+    }
+
+    pub fn group_set_value(
+        &mut self,
+        name: &str,
+        value: f64,
+    ) -> Result<Vec<BulkOutcome>, SmartHouseError> {
+        self.for_each_in_group(name, |device| match device.set_value(value) {
+            true => Ok(()),
+            false => Err(SmartHouseError::Unsupported(format!(
+                "{:?} does not accept value {}",
+                device.device_type(),
+                value
+            ))),
+        })
+    }
+
+    fn for_each_in_group(
+        &mut self,
+        name: &str,
+        mut action: impl FnMut(&mut SmartDevice) -> Result<(), SmartHouseError>,
+    ) -> Result<Vec<BulkOutcome>, SmartHouseError> {
+        let members = self
+            .groups
+            .get(name)
+            .ok_or_else(|| SmartHouseError::GroupNotFound(name.to_string()))?
+            .clone();
+        Ok(members
+            .into_iter()
+            .map(|(room, device)| {
+                let result = self
+                    .rooms
+                    .get_mut(&room)
+                    .ok_or_else(|| SmartHouseError::RoomNotFound(room.clone()))
+                    .and_then(|r| {
+                        r.get_mut_device(&device)
+                            .ok_or_else(|| SmartHouseError::DeviceNotFound(device.clone()))
+                    })
+                    .and_then(&mut action);
+                BulkOutcome {
+                    room,
+                    device,
+                    result,
+                }
+            })
+            .collect())
+    }
+
+    pub fn add_scene(&mut self, scene: Scene) {
+        self.scenes.insert(scene.name.clone(), scene);
+    }
+
+    pub fn del_scene(&mut self, name: &str) -> Result<Scene, SmartHouseError> {
+        self.scenes
+            .shift_remove(name)
+            .ok_or_else(|| SmartHouseError::SceneNotFound(name.to_string()))
+    }
+
+    pub fn get_scene(&self, name: &str) -> Option<&Scene> {
+        self.scenes.get(name)
+    }
+
+    pub fn get_scenes_names(&self) -> Vec<String> {
+        self.scenes.keys().cloned().collect()
+    }
+
+    pub fn active_alarm(&self) -> Option<&AlarmEvent> {
+        self.alarm.as_ref()
+    }
+
+    /// Looks for triggered detectors and, on a new alarm, sounds every detector
+    /// in the house and switches off the registered shutoff devices.
+    pub fn check_alarms(&mut self) -> Option<AlarmEvent> {
+        if self.alarm.is_some() {
+            return None;
+        }
+        let sources: Vec<(String, String)> = self
+            .devices()
+            .filter(|(_, _, device)| device.is_alarm())
+            .map(|(room_name, name, _)| (room_name.to_string(), name.to_string()))
+            .collect();
+        if sources.is_empty() {
+            return None;
+        }
+
+        for (_, _, device) in self.devices_mut() {
+            if device.is_detector() {
+                device.trigger_alarm();
+            }
+        }
+
+        let mut switched_off = Vec::new();
+        for (room_name, device_name) in &self.alarm_shutoffs {
+            if let Some(device) = self
+                .rooms
+                .get_mut(room_name)
+                .and_then(|room| room.get_mut_device(device_name))
+            {
+                device.turn_off();
+                switched_off.push((room_name.clone(), device_name.clone()));
+            }
+        }
+
+        let event = AlarmEvent {
+            sources,
+            switched_off,
+        };
+        self.alarm = Some(event.clone());
+        Some(event)
+    }
+
+    /// Silences all detectors. Shutoff devices stay off until turned on again.
+    pub fn acknowledge_alarm(&mut self) -> Option<AlarmEvent> {
+        for (_, _, device) in self.devices_mut() {
+            if device.is_detector() {
+                device.clear_alarm();
+            }
+        }
+        self.alarm.take()
+    }
 }
 
 impl Reportable for House {
     fn generate_report(&self) -> String {
-        self.rooms
-            .iter()
-            .map(|(name, device)| format!("Room: {}\n{}\n", name, device.generate_report()))
-            .collect::<Vec<String>>()
-            .join("\n")
+        TextRenderer.render(&HouseReport::from_house(self), &ReportOptions::default())
     }
 }
 
 impl Report for House {
     fn report(&self) -> String {
         format!(
-            "House contains {} rooms witch names: {:?}",
+            "House contains {} rooms witch names: {:?}, {} of {} devices are on",
             self.rooms.len(),
-            self.get_rooms_names()
+            self.get_rooms_names(),
+            self.query(&DeviceQuery::new().on()).count(),
+            self.devices().count()
         )
     }
 }
@@ -105,13 +570,151 @@ mod tests {
         let mut house = House::new();
         assert!(!house.rooms.contains_key("Test room"));
 
-        house.add_room("Test room", None);
+        house.add_room("Test room", None).unwrap();
         assert!(house.rooms.contains_key("Test room"));
 
-        house.del_room("Test room");
+        house.del_room("Test room").unwrap();
         assert!(!house.rooms.contains_key("Test room"));
     }
 
+    #[test]
+    fn test_add_duplicate_room() {
+        let mut house = House::new();
+        house.add_room("Test room", None).unwrap();
+        assert_eq!(
+            house.add_room("Test room", None),
+            Err(SmartHouseError::DuplicateName("Test room".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_rooms_keep_insertion_order() {
+        let mut house = House::new();
+        for name in ["Kitchen", "Hall", "Bedroom", "Attic"] {
+            house.add_room(name, None).unwrap();
+        }
+        house.del_room("Hall").unwrap();
+        assert_eq!(house.get_rooms_names(), vec!["Kitchen", "Bedroom", "Attic"]);
+
+        house.move_room("Attic", 0).unwrap();
+        house.move_room("Kitchen", 10).unwrap();
+        assert_eq!(house.get_rooms_names(), vec!["Attic", "Bedroom", "Kitchen"]);
+        assert!(house.move_room("Hall", 0).is_err());
+    }
+
+    #[test]
+    fn test_move_device_between_rooms() {
+        let mut house = alarm_house();
+        house.move_device("Kitchen", "Heater", "Hall").unwrap();
+
+        assert!(house.get_device("Kitchen", "Heater").is_err());
+        let heater = house.get_device("Hall", "Heater").unwrap();
+        assert_eq!(heater.get_value(), 2000.0);
+        assert_eq!(
+            house.get_alarm_shutoffs(),
+            &[("Hall".to_string(), "Heater".to_string())]
+        );
+        assert_eq!(
+            house.move_device("Hall", "Detector", "Kitchen"),
+            Err(SmartHouseError::DuplicateName("Detector".to_string()))
+        );
+        assert!(house.get_device("Hall", "Detector").is_ok());
+    }
+
+    #[test]
+    fn test_rename_room_and_device() {
+        let mut house = alarm_house();
+        house.rename_room("Kitchen", "Galley").unwrap();
+        house.rename_device("Galley", "Heater", "Radiator").unwrap();
+
+        assert_eq!(house.get_rooms_names(), vec!["Galley", "Hall"]);
+        assert_eq!(
+            house.get_device("Galley", "Radiator").unwrap().get_value(),
+            2000.0
+        );
+        assert_eq!(
+            house.get_alarm_shutoffs(),
+            &[("Galley".to_string(), "Radiator".to_string())]
+        );
+        assert!(house.rename_room("Galley", "Hall").is_err());
+        assert!(matches!(
+            house.rename_room("Galley", "Up/Down"),
+            Err(SmartHouseError::InvalidParameter(_))
+        ));
+        assert!(house.add_room("Up/Down", None).is_err());
+        assert_eq!(
+            house.rename_room("Galley", " "),
+            Err(SmartHouseError::EmptyName("room".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_duplicate_room() {
+        let mut house = alarm_house();
+        house.duplicate_room("Hall", "Hall copy").unwrap();
+
+        let lamp = house.get_device("Hall copy", "Lamp").unwrap();
+        assert_eq!(lamp.get_value(), 60.0);
+        assert!(house.duplicate_room("Hall", "Kitchen").is_err());
+        assert!(house.duplicate_room("Attic", "Loft").is_err());
+    }
+
+    #[test]
+    fn test_group_bulk_operations() {
+        let mut house = alarm_house();
+        house.create_group("Power").unwrap();
+        house.add_to_group("Power", "Kitchen", "Heater").unwrap();
+        house.add_to_group("Power", "Hall", "Lamp").unwrap();
+        house.add_to_group("Power", "Hall", "Detector").unwrap();
+
+        let outcomes = house.group_turn_off("Power").unwrap();
+        assert!(outcomes.iter().all(|outcome| outcome.result.is_ok()));
+        assert_eq!(house.get_device("Hall", "Lamp").unwrap().get_value(), 0.0);
+
+        house.group_turn_on("Power").unwrap();
+        let outcomes = house.group_set_value("Power", 100.0).unwrap();
+        assert!(outcomes[0].result.is_ok());
+        assert!(outcomes[2].result.is_err());
+        assert_eq!(house.get_device("Hall", "Lamp").unwrap().get_value(), 100.0);
+    }
+
+    #[test]
+    fn test_group_reports_missing_members() {
+        let mut house = alarm_house();
+        house.create_group("Hall").unwrap();
+        house.add_to_group("Hall", "Hall", "Lamp").unwrap();
+        assert!(house.add_to_group("Hall", "Hall", "Oven").is_err());
+        house.rename_device("Hall", "Lamp", "Light").unwrap();
+        assert_eq!(
+            house.get_group("Hall").unwrap(),
+            &[("Hall".to_string(), "Light".to_string())]
+        );
+
+        house
+            .get_mut_room("Hall")
+            .unwrap()
+            .del_device("Light")
+            .unwrap();
+        let outcomes = house.group_turn_on("Hall").unwrap();
+        assert_eq!(
+            outcomes[0].result,
+            Err(SmartHouseError::DeviceNotFound("Light".to_string()))
+        );
+        assert!(matches!(
+            house.group_turn_on("Attic"),
+            Err(SmartHouseError::GroupNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_del_missing_room() {
+        let mut house = House::new();
+        assert!(matches!(
+            house.del_room("Test room"),
+            Err(SmartHouseError::RoomNotFound(_))
+        ));
+    }
+
     #[test]
     fn test_get_device_room_not_found() {
         let house = House::new();
@@ -120,4 +723,127 @@ mod tests {
         assert!(result.is_err());
         assert_eq!(result.unwrap_err().to_string(), "Room not found: kitchen");
     }
+
+    fn alarm_house() -> House {
+        let mut house = House::new();
+        house.add_room("Kitchen", None).unwrap();
+        house.add_room("Hall", None).unwrap();
+        let kitchen = house.get_mut_room("Kitchen").unwrap();
+        kitchen
+            .add_device("Detector", SmartDevice::smoke_detector())
+            .unwrap();
+        kitchen
+            .add_device("Heater", SmartDevice::power_socket(2000.0))
+            .unwrap();
+        kitchen.get_mut_device("Heater").unwrap().turn_on();
+        let hall = house.get_mut_room("Hall").unwrap();
+        hall.add_device("Detector", SmartDevice::smoke_detector())
+            .unwrap();
+        hall.add_device("Lamp", SmartDevice::power_socket(60.0))
+            .unwrap();
+        hall.get_mut_device("Lamp").unwrap().turn_on();
+        house.add_alarm_shutoff("Kitchen", "Heater").unwrap();
+        house
+    }
+
+    #[test]
+    fn test_alarm_propagates_across_house() {
+        let mut house = alarm_house();
+        assert!(house.check_alarms().is_none());
+
+        house
+            .get_mut_room("Kitchen")
+            .unwrap()
+            .get_mut_device("Detector")
+            .unwrap()
+            .trigger_alarm();
+
+        let event = house.check_alarms().unwrap();
+        assert_eq!(
+            event.sources,
+            vec![("Kitchen".to_string(), "Detector".to_string())]
+        );
+        assert_eq!(
+            event.switched_off,
+            vec![("Kitchen".to_string(), "Heater".to_string())]
+        );
+        assert!(house.get_device("Hall", "Detector").unwrap().is_alarm());
+        assert_eq!(
+            house.get_device("Kitchen", "Heater").unwrap().get_value(),
+            0.0
+        );
+        assert_eq!(house.get_device("Hall", "Lamp").unwrap().get_value(), 60.0);
+        assert!(house.check_alarms().is_none());
+    }
+
+    #[test]
+    fn test_acknowledge_alarm_clears_detectors() {
+        let mut house = alarm_house();
+        house
+            .get_mut_room("Hall")
+            .unwrap()
+            .get_mut_device("Detector")
+            .unwrap()
+            .trigger_alarm();
+        house.check_alarms();
+
+        assert!(house.acknowledge_alarm().is_some());
+        assert!(house.active_alarm().is_none());
+        assert!(!house.get_device("Kitchen", "Detector").unwrap().is_alarm());
+        assert!(!house.get_device("Hall", "Detector").unwrap().is_alarm());
+        assert_eq!(
+            house.get_device("Kitchen", "Heater").unwrap().get_value(),
+            0.0
+        );
+    }
+
+    #[test]
+    fn test_alarm_shutoff_requires_existing_device() {
+        let mut house = alarm_house();
+        assert!(house.add_alarm_shutoff("Kitchen", "Oven").is_err());
+    }
+
+    #[test]
+    fn test_house_aggregates() {
+        let mut house = alarm_house();
+        house
+            .get_mut_room("Hall")
+            .unwrap()
+            .get_mut_device("Lamp")
+            .unwrap()
+            .turn_off();
+
+        let kitchen = house.get_room("Kitchen").unwrap().aggregates();
+        assert_eq!(kitchen.devices_on, 2);
+        assert_eq!(kitchen.power, 2000.0);
+
+        let total = house.aggregates();
+        assert_eq!(total.devices, 4);
+        assert_eq!(total.devices_on, 3);
+        assert_eq!(total.power, 2000.0);
+        assert!(
+            house
+                .generate_report()
+                .contains("House total: 3 of 4 devices on")
+        );
+    }
+
+    #[test]
+    fn test_house_macro() {
+        let hall = crate::room! { "Lamp": power_socket(60.0) on };
+        let house = house! {
+            "Kitchen" {
+                tags ["ground"],
+                "Heater": power_socket(2000.0) off ["heating"],
+                "Detector": smoke_detector(),
+            },
+            "Hall": hall,
+            "Attic" {},
+        };
+        assert_eq!(house.get_rooms_names(), vec!["Kitchen", "Hall", "Attic"]);
+        let kitchen = house.get_room("Kitchen").unwrap();
+        assert!(kitchen.labels().has_tag("ground"));
+        assert!(!kitchen.get_device("Heater").unwrap().is_on());
+        assert!(house.get_device("Hall", "Lamp").unwrap().is_on());
+    }
 }
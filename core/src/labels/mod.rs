@@ -0,0 +1,90 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Display, Formatter};
+
+/// User-defined tags and key/value metadata of a device or a room.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Labels {
+    tags: BTreeSet<String>,
+    metadata: BTreeMap<String, String>,
+}
+
+impl Labels {
+    pub fn new() -> Self {
+        Labels::default()
+    }
+
+    pub fn add_tag(&mut self, tag: &str) -> bool {
+        self.tags.insert(tag.to_string())
+    }
+
+    pub fn remove_tag(&mut self, tag: &str) -> bool {
+        self.tags.remove(tag)
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.contains(tag)
+    }
+
+    pub fn tags(&self) -> impl Iterator<Item = &str> {
+        self.tags.iter().map(String::as_str)
+    }
+
+    pub fn set_meta(&mut self, key: &str, value: &str) -> Option<String> {
+        self.metadata.insert(key.to_string(), value.to_string())
+    }
+
+    pub fn get_meta(&self, key: &str) -> Option<&str> {
+        self.metadata.get(key).map(String::as_str)
+    }
+
+    pub fn remove_meta(&mut self, key: &str) -> Option<String> {
+        self.metadata.remove(key)
+    }
+
+    pub fn metadata(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.metadata.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty() && self.metadata.is_empty()
+    }
+}
+
+impl Display for Labels {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let labels: Vec<String> = self
+            .tags()
+            .map(|tag| format!("#{}", tag))
+            .chain(self.metadata().map(|(k, v)| format!("{}={}", k, v)))
+            .collect();
+        write!(f, "{}", labels.join(" "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tags_and_metadata() {
+        let mut labels = Labels::new();
+        assert!(labels.is_empty());
+        assert!(labels.add_tag("downstairs"));
+        assert!(!labels.add_tag("downstairs"));
+        labels.add_tag("light");
+        labels.set_meta("vendor", "Acme");
+        assert_eq!(
+            labels.set_meta("vendor", "Globex"),
+            Some("Acme".to_string())
+        );
+
+        assert!(labels.has_tag("light"));
+        assert_eq!(labels.get_meta("vendor"), Some("Globex"));
+        assert_eq!(labels.to_string(), "#downstairs #light vendor=Globex");
+
+        assert!(labels.remove_tag("light"));
+        assert_eq!(labels.remove_meta("vendor"), Some("Globex".to_string()));
+        assert_eq!(labels.to_string(), "#downstairs");
+    }
+}
@@ -1,5 +1,15 @@
+pub mod aggregate;
+pub mod billing;
 pub mod builder;
+pub mod clock;
+pub mod config;
+pub mod error;
+pub mod history;
 pub mod house;
+pub mod labels;
+pub mod metrics;
+pub mod query;
 pub mod report;
 pub mod room;
+pub mod scene;
 pub mod smart_device;
@@ -1,21 +1,137 @@
+use clap::{Args, Parser, Subcommand};
 use smart_house_lib::builder::HouseBuilder;
+use smart_house_lib::error::SmartHouseError;
+use smart_house_lib::history::History;
+use smart_house_lib::house::House;
+use smart_house_lib::metrics::MetricsEndpoint;
 use smart_house_lib::smart_device::SmartDevice;
+use std::path::PathBuf;
+use std::process::ExitCode;
 mod cli;
+use cli::commands::{self, HouseCommand};
 use cli::run_cli_loop;
 
-fn main() {
-    let house = HouseBuilder::new()
+/// Long enough for a monthly energy report.
+const HISTORY_RETENTION: u64 = 31 * 24 * 3600;
+
+/// Without a subcommand, starts the interactive shell.
+#[derive(Parser)]
+#[command(name = "smart_house", args_conflicts_with_subcommands = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    #[command(flatten)]
+    interactive: InteractiveArgs,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Run the interactive shell
+    Interactive(InteractiveArgs),
+    #[command(flatten)]
+    House(HouseCommand),
+}
+
+#[derive(Args)]
+struct InteractiveArgs {
+    /// Build the house from a TOML file
+    #[arg(long)]
+    config: Option<PathBuf>,
+    /// Serve Prometheus metrics at this loopback address
+    #[arg(long)]
+    metrics: Option<String>,
+    /// Keep device history in this JSON Lines file
+    #[arg(long)]
+    history: Option<PathBuf>,
+    /// Sample every device at least this often, in seconds
+    #[arg(long, default_value_t = 60, value_parser = clap::value_parser!(u64).range(1..))]
+    history_interval: u64,
+}
+
+fn default_house() -> Result<House, Vec<SmartHouseError>> {
+    HouseBuilder::new()
         .add_room("First room")
         .add_device("PowerSocket_1", SmartDevice::power_socket(40.0))
         .add_device("PowerSocket_2", SmartDevice::power_socket(60.0))
-        .add_device("Thermometer_1", SmartDevice::thermometer(23.0))
+        .try_add_device("Thermometer_1", SmartDevice::try_thermometer(23.0))
         .build()
         .add_room("Second room")
         .add_device("PowerSocket_1", SmartDevice::power_socket(40.0))
-        .add_device("Thermometer_1", SmartDevice::thermometer(23.2))
+        .try_add_device("Thermometer_1", SmartDevice::try_thermometer(23.2))
+        .add_device("DoorLock_1", SmartDevice::door_lock())
+        .add_device("Blind_1", SmartDevice::blind(0.0))
+        .add_device("SmokeDetector_1", SmartDevice::smoke_detector())
         .build()
-        .build();
+        .add_alarm_shutoff("First room", "PowerSocket_2")
+        .try_build()
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match cli.command {
+        None => run_interactive(cli.interactive),
+        Some(Command::Interactive(args)) => run_interactive(args),
+        Some(Command::House(command)) => match commands::run(&command) {
+            Ok(output) => {
+                print!("{}", output);
+                ExitCode::SUCCESS
+            }
+            Err(err) => {
+                eprintln!("Error: {}", err);
+                ExitCode::from(commands::exit_code(&err))
+            }
+        },
+    }
+}
+
+fn run_interactive(args: InteractiveArgs) -> ExitCode {
+    let InteractiveArgs {
+        config,
+        metrics: metrics_addr,
+        history: history_path,
+        history_interval,
+    } = args;
+    let house = match config {
+        None => default_house(),
+        Some(path) => HouseBuilder::from_toml_file(path)
+            .map_err(|err| vec![err])
+            .and_then(HouseBuilder::try_build),
+    };
+    let house = match house {
+        Ok(house) => house,
+        Err(errors) => {
+            for err in errors {
+                eprintln!("{}", err);
+            }
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let metrics = match metrics_addr.map(MetricsEndpoint::serve).transpose() {
+        Ok(metrics) => metrics,
+        Err(err) => {
+            eprintln!("Metrics endpoint not started: {}", err);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let capacity = (HISTORY_RETENTION / history_interval) as usize;
+    let history = match history_path {
+        None => Ok(History::new(capacity, history_interval)),
+        Some(path) => History::open(path, capacity, history_interval),
+    };
+    let history = match history {
+        Ok(history) => history,
+        Err(err) => {
+            eprintln!("{}", err);
+            return ExitCode::FAILURE;
+        }
+    };
 
     println!("Smart House CLI started!");
-    run_cli_loop(house);
+    if let Some(metrics) = &metrics {
+        println!("Serving metrics at http://{}/metrics", metrics.local_addr());
+    }
+    run_cli_loop(house, metrics, history);
+    ExitCode::SUCCESS
 }
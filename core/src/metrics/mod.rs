@@ -0,0 +1,191 @@
+use crate::house::House;
+use crate::smart_device::DeviceType;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::{Arc, RwLock};
+use std::thread;
+use std::time::Duration;
+
+/// How long a scraper may take to send its request or read the response.
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Renders every device of the house in the Prometheus text exposition format.
+pub fn render_metrics(house: &House) -> String {
+    let mut value = String::new();
+    let mut on = String::new();
+    let mut energy = String::new();
+    for (room_name, device_name, device) in house.devices() {
+        let labels = format!(
+            "room=\"{}\",device=\"{}\",type=\"{:?}\"",
+            escape_label(room_name),
+            escape_label(device_name),
+            device.device_type()
+        );
+        value.push_str(&format!(
+            "smart_house_device_value{{{}}} {}\n",
+            labels,
+            sample(device.get_value())
+        ));
+        on.push_str(&format!(
+            "smart_house_device_on{{{}}} {}\n",
+            labels,
+            device.is_on() as u8
+        ));
+        if device.device_type() == DeviceType::PowerSocket {
+            energy.push_str(&format!(
+                "smart_house_device_energy_wh_total{{{}}} {}\n",
+                labels,
+                sample(device.get_energy())
+            ));
+        }
+    }
+    format!(
+        "# HELP smart_house_device_value Current device reading: watts, degrees, blind position or a flag.\n\
+         # TYPE smart_house_device_value gauge\n{}\
+         # HELP smart_house_device_on Whether the device is switched on.\n\
+         # TYPE smart_house_device_on gauge\n{}\
+         # HELP smart_house_device_energy_wh_total Energy consumed by power sockets, in watt-hours.\n\
+         # TYPE smart_house_device_energy_wh_total counter\n{}",
+        value, on, energy
+    )
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn sample(value: f64) -> String {
+    match value {
+        v if v.is_nan() => "NaN".to_string(),
+        v if v == f64::INFINITY => "+Inf".to_string(),
+        v if v == f64::NEG_INFINITY => "-Inf".to_string(),
+        v => v.to_string(),
+    }
+}
+
+/// HTTP endpoint serving the last published snapshot at `/metrics`.
+#[derive(Clone)]
+pub struct MetricsEndpoint {
+    addr: SocketAddr,
+    body: Arc<RwLock<String>>,
+}
+
+impl MetricsEndpoint {
+    /// Binds `addr`, which must be a loopback address, and answers each scrape
+    /// on its own thread.
+    pub fn serve(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        let addrs: Vec<SocketAddr> = addr.to_socket_addrs()?.collect();
+        if let Some(addr) = addrs.iter().find(|addr| !addr.ip().is_loopback()) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} is not a loopback address", addr),
+            ));
+        }
+        let listener = TcpListener::bind(&addrs[..])?;
+        let endpoint = MetricsEndpoint {
+            addr: listener.local_addr()?,
+            body: Arc::new(RwLock::new(String::new())),
+        };
+        let body = Arc::clone(&endpoint.body);
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let body = Arc::clone(&body);
+                // A broken or stalled scrape only affects that client.
+                thread::spawn(move || respond(stream, &body));
+            }
+        });
+        Ok(endpoint)
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Replaces the snapshot returned to scrapers.
+    pub fn publish(&self, house: &House) {
+        *self.body.write().unwrap() = render_metrics(house);
+    }
+}
+
+fn respond(mut stream: TcpStream, body: &RwLock<String>) -> io::Result<()> {
+    stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+    stream.set_write_timeout(Some(CLIENT_TIMEOUT))?;
+    let mut reader = BufReader::new(&stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let response = match (parts.next(), parts.next()) {
+        (Some("GET"), Some("/metrics")) => {
+            let body = body.read().unwrap();
+            format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\n\
+                 Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            )
+        }
+        _ => "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string(),
+    };
+    stream.write_all(response.as_bytes())?;
+    stream.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn test_render_metrics() {
+        let house = crate::house! {
+            "Living \"room\"" {
+                "Lamp": power_socket(60.0) on,
+                "Detector": smoke_detector(),
+            }
+        };
+        let metrics = render_metrics(&house);
+        let labels = "room=\"Living \\\"room\\\"\",device=\"Lamp\",type=\"PowerSocket\"";
+        assert!(metrics.contains(&format!("smart_house_device_value{{{}}} 60\n", labels)));
+        assert!(metrics.contains(&format!("smart_house_device_on{{{}}} 1\n", labels)));
+        assert!(metrics.contains(&format!("smart_house_device_energy_wh_total{{{}}}", labels)));
+        assert!(!metrics.contains(
+            "smart_house_device_energy_wh_total{room=\"Living \\\"room\\\"\",device=\"Detector\""
+        ));
+    }
+
+    #[test]
+    fn test_endpoint_serves_published_metrics() {
+        let endpoint = MetricsEndpoint::serve("127.0.0.1:0").unwrap();
+        endpoint.publish(&crate::house! { "Hall" { "Lamp": power_socket(60.0) on } });
+
+        let _stalled = TcpStream::connect(endpoint.local_addr()).unwrap();
+        let mut stream = TcpStream::connect(endpoint.local_addr()).unwrap();
+        stream
+            .write_all(b"GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("smart_house_device_on{"));
+
+        let mut stream = TcpStream::connect(endpoint.local_addr()).unwrap();
+        stream.write_all(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 404"));
+    }
+
+    #[test]
+    fn test_endpoint_rejects_non_loopback_address() {
+        let err = MetricsEndpoint::serve("0.0.0.0:0").err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+}
@@ -0,0 +1,198 @@
+use crate::room::Room;
+use crate::smart_device::{DeviceType, SmartDevice};
+
+/// Filter over house devices; unset criteria match everything.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DeviceQuery {
+    device_type: Option<DeviceType>,
+    is_on: Option<bool>,
+    room: Option<String>,
+    min_value: Option<f64>,
+    max_value: Option<f64>,
+    tags: Vec<String>,
+    metadata: Vec<(String, String)>,
+}
+
+impl DeviceQuery {
+    pub fn new() -> Self {
+        DeviceQuery::default()
+    }
+
+    pub fn of_type(mut self, device_type: DeviceType) -> Self {
+        self.device_type = Some(device_type);
+        self
+    }
+
+    pub fn on(mut self) -> Self {
+        self.is_on = Some(true);
+        self
+    }
+
+    pub fn off(mut self) -> Self {
+        self.is_on = Some(false);
+        self
+    }
+
+    pub fn in_room(mut self, room_name: &str) -> Self {
+        self.room = Some(room_name.to_string());
+        self
+    }
+
+    pub fn min_value(mut self, value: f64) -> Self {
+        self.min_value = Some(value);
+        self
+    }
+
+    pub fn max_value(mut self, value: f64) -> Self {
+        self.max_value = Some(value);
+        self
+    }
+
+    /// Requires the tag on the device or on its room.
+    pub fn tagged(mut self, tag: &str) -> Self {
+        self.tags.push(tag.to_string());
+        self
+    }
+
+    /// Requires the metadata entry on the device or on its room.
+    pub fn with_meta(mut self, key: &str, value: &str) -> Self {
+        self.metadata.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn matches(&self, room_name: &str, room: &Room, device: &SmartDevice) -> bool {
+        if self.room.as_deref().is_some_and(|room| room != room_name) {
+            return false;
+        }
+        let labels = [device.labels(), room.labels()];
+        if !self
+            .tags
+            .iter()
+            .all(|tag| labels.iter().any(|l| l.has_tag(tag)))
+        {
+            return false;
+        }
+        if !self
+            .metadata
+            .iter()
+            .all(|(key, value)| labels.iter().any(|l| l.get_meta(key) == Some(value)))
+        {
+            return false;
+        }
+        if self
+            .device_type
+            .is_some_and(|device_type| device_type != device.device_type())
+        {
+            return false;
+        }
+        if self.is_on.is_some_and(|is_on| is_on != device.is_on()) {
+            return false;
+        }
+        if self.min_value.is_none() && self.max_value.is_none() {
+            return true;
+        }
+        let value = device.get_value();
+        self.min_value.is_none_or(|min| value >= min)
+            && self.max_value.is_none_or(|max| value <= max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::house::House;
+
+    fn sample_house() -> House {
+        let mut house = House::new();
+        house.add_room("Office", None).unwrap();
+        house.add_room("Kitchen", None).unwrap();
+        let office = house.get_mut_room("Office").unwrap();
+        office
+            .add_device("Heater", SmartDevice::power_socket(1500.0))
+            .unwrap();
+        office
+            .add_device("Lamp", SmartDevice::power_socket(40.0))
+            .unwrap();
+        office.add_device("Door", SmartDevice::door_lock()).unwrap();
+        let kitchen = house.get_mut_room("Kitchen").unwrap();
+        kitchen
+            .add_device("Kettle", SmartDevice::power_socket(2000.0))
+            .unwrap();
+        for (_, _, device) in house.devices_mut() {
+            if device.device_type() == DeviceType::PowerSocket {
+                device.turn_on();
+            }
+        }
+        house
+            .get_mut_room("Kitchen")
+            .unwrap()
+            .get_mut_device("Kettle")
+            .unwrap()
+            .turn_off();
+        house
+    }
+
+    fn names(house: &House, query: &DeviceQuery) -> Vec<String> {
+        house
+            .query(query)
+            .map(|(room, name, _)| format!("{}/{}", room, name))
+            .collect()
+    }
+
+    #[test]
+    fn test_devices_iterates_all_rooms_in_order() {
+        let house = sample_house();
+        assert_eq!(
+            names(&house, &DeviceQuery::new()),
+            vec![
+                "Office/Heater",
+                "Office/Lamp",
+                "Office/Door",
+                "Kitchen/Kettle"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_query_by_tags_and_metadata() {
+        let mut house = sample_house();
+        let office = house.get_mut_room("Office").unwrap();
+        office.labels_mut().add_tag("downstairs");
+        office
+            .get_mut_device("Lamp")
+            .unwrap()
+            .labels_mut()
+            .add_tag("light");
+        house
+            .get_mut_room("Kitchen")
+            .unwrap()
+            .get_mut_device("Kettle")
+            .unwrap()
+            .labels_mut()
+            .set_meta("vendor", "Acme");
+
+        let query = DeviceQuery::new().tagged("downstairs").tagged("light");
+        assert_eq!(names(&house, &query), vec!["Office/Lamp"]);
+        let query = DeviceQuery::new().with_meta("vendor", "Acme");
+        assert_eq!(names(&house, &query), vec!["Kitchen/Kettle"]);
+
+        let query = DeviceQuery::new().tagged("downstairs");
+        for (_, _, device) in house.query_mut(&query) {
+            device.turn_off();
+        }
+        assert_eq!(house.query(&DeviceQuery::new().on()).count(), 0);
+    }
+
+    #[test]
+    fn test_query_sockets_drawing_power() {
+        let house = sample_house();
+        let query = DeviceQuery::new()
+            .of_type(DeviceType::PowerSocket)
+            .on()
+            .min_value(100.0);
+        assert_eq!(names(&house, &query), vec!["Office/Heater"]);
+
+        let query = DeviceQuery::new().off().in_room("Kitchen");
+        assert_eq!(names(&house, &query), vec!["Kitchen/Kettle"]);
+    }
+}
@@ -0,0 +1,272 @@
+use crate::error::SmartHouseError;
+use crate::house::House;
+use crate::report::{HouseReport, RoomReport};
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display, Formatter};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum Change {
+    RoomAdded {
+        room: String,
+    },
+    RoomRemoved {
+        room: String,
+    },
+    DeviceAdded {
+        room: String,
+        device: String,
+    },
+    DeviceRemoved {
+        room: String,
+        device: String,
+    },
+    StateChanged {
+        room: String,
+        device: String,
+        from: String,
+        to: String,
+    },
+    ValueChanged {
+        room: String,
+        device: String,
+        from: f64,
+        to: f64,
+    },
+}
+
+/// What changed between two snapshots of a house.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ReportDiff {
+    pub changes: Vec<Change>,
+}
+
+impl ReportDiff {
+    /// Compares two reports; values differing by no more than `threshold` count as equal.
+    pub fn between(before: &HouseReport, after: &HouseReport, threshold: f64) -> Self {
+        let mut changes = Vec::new();
+        for old_room in &before.rooms {
+            match find_room(after, &old_room.name) {
+                None => {
+                    changes.push(Change::RoomRemoved {
+                        room: old_room.name.clone(),
+                    });
+                    changes.extend(old_room.devices.iter().map(|device| Change::DeviceRemoved {
+                        room: old_room.name.clone(),
+                        device: device.name.clone(),
+                    }));
+                }
+                Some(new_room) => diff_rooms(old_room, new_room, threshold, &mut changes),
+            }
+        }
+        for new_room in &after.rooms {
+            if find_room(before, &new_room.name).is_none() {
+                changes.push(Change::RoomAdded {
+                    room: new_room.name.clone(),
+                });
+                changes.extend(new_room.devices.iter().map(|device| Change::DeviceAdded {
+                    room: new_room.name.clone(),
+                    device: device.name.clone(),
+                }));
+            }
+        }
+        ReportDiff { changes }
+    }
+
+    pub fn between_houses(before: &House, after: &House, threshold: f64) -> Self {
+        ReportDiff::between(
+            &HouseReport::from_house(before),
+            &HouseReport::from_house(after),
+            threshold,
+        )
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    pub fn to_json(&self) -> Result<String, SmartHouseError> {
+        serde_json::to_string_pretty(self).map_err(|err| SmartHouseError::Storage(err.to_string()))
+    }
+}
+
+fn find_room<'a>(report: &'a HouseReport, name: &str) -> Option<&'a RoomReport> {
+    report.rooms.iter().find(|room| room.name == name)
+}
+
+fn diff_rooms(before: &RoomReport, after: &RoomReport, threshold: f64, changes: &mut Vec<Change>) {
+    let room = || before.name.clone();
+    for old in &before.devices {
+        let Some(new) = after.devices.iter().find(|device| device.name == old.name) else {
+            changes.push(Change::DeviceRemoved {
+                room: room(),
+                device: old.name.clone(),
+            });
+            continue;
+        };
+        if old.state != new.state {
+            changes.push(Change::StateChanged {
+                room: room(),
+                device: old.name.clone(),
+                from: old.state.clone(),
+                to: new.state.clone(),
+            });
+        }
+        if (old.value - new.value).abs() > threshold {
+            changes.push(Change::ValueChanged {
+                room: room(),
+                device: old.name.clone(),
+                from: old.value,
+                to: new.value,
+            });
+        }
+    }
+    for new in &after.devices {
+        if !before.devices.iter().any(|device| device.name == new.name) {
+            changes.push(Change::DeviceAdded {
+                room: room(),
+                device: new.name.clone(),
+            });
+        }
+    }
+}
+
+impl Display for Change {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Change::RoomAdded { room } => write!(f, "+ room '{}'", room),
+            Change::RoomRemoved { room } => write!(f, "- room '{}'", room),
+            Change::DeviceAdded { room, device } => write!(f, "+ device '{}/{}'", room, device),
+            Change::DeviceRemoved { room, device } => {
+                write!(f, "- device '{}/{}'", room, device)
+            }
+            Change::StateChanged {
+                room,
+                device,
+                from,
+                to,
+            } => write!(f, "~ '{}/{}' state {} -> {}", room, device, from, to),
+            Change::ValueChanged {
+                room,
+                device,
+                from,
+                to,
+            } => write!(f, "~ '{}/{}' value {} -> {}", room, device, from, to),
+        }
+    }
+}
+
+impl Display for ReportDiff {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "No changes");
+        }
+        for change in &self.changes {
+            writeln!(f, "{}", change)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::house;
+
+    #[test]
+    fn test_diff_between_houses() {
+        let before = house! {
+            "Kitchen" {
+                "Heater": power_socket(2000.0),
+                "Kettle": power_socket(1500.0),
+            },
+            "Hall" {},
+        };
+        let after = house! {
+            "Kitchen" {
+                "Heater": power_socket(2000.0) on,
+                "Lamp": power_socket(60.0),
+            },
+            "Attic" {},
+        };
+
+        let diff = ReportDiff::between_houses(&before, &after, 0.5);
+        let kitchen = || "Kitchen".to_string();
+        assert_eq!(
+            diff.changes,
+            vec![
+                Change::StateChanged {
+                    room: kitchen(),
+                    device: "Heater".to_string(),
+                    from: "OFF".to_string(),
+                    to: "ON".to_string(),
+                },
+                Change::ValueChanged {
+                    room: kitchen(),
+                    device: "Heater".to_string(),
+                    from: 0.0,
+                    to: 2000.0,
+                },
+                Change::DeviceRemoved {
+                    room: kitchen(),
+                    device: "Kettle".to_string(),
+                },
+                Change::DeviceAdded {
+                    room: kitchen(),
+                    device: "Lamp".to_string(),
+                },
+                Change::RoomRemoved {
+                    room: "Hall".to_string(),
+                },
+                Change::RoomAdded {
+                    room: "Attic".to_string(),
+                },
+            ]
+        );
+        assert!(
+            diff.to_string()
+                .contains("~ 'Kitchen/Heater' state OFF -> ON\n")
+        );
+    }
+
+    #[test]
+    fn test_diff_lists_devices_of_added_and_removed_rooms() {
+        let before = house! { "Hall" { "Lamp": power_socket(60.0) } };
+        let after = house! { "Attic" { "Fan": power_socket(40.0) } };
+        let diff = ReportDiff::between_houses(&before, &after, 0.0);
+        assert_eq!(
+            diff.changes,
+            vec![
+                Change::RoomRemoved {
+                    room: "Hall".to_string(),
+                },
+                Change::DeviceRemoved {
+                    room: "Hall".to_string(),
+                    device: "Lamp".to_string(),
+                },
+                Change::RoomAdded {
+                    room: "Attic".to_string(),
+                },
+                Change::DeviceAdded {
+                    room: "Attic".to_string(),
+                    device: "Fan".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diff_threshold_and_json() {
+        let before =
+            HouseReport::from_house(&house! { "Kitchen" { "Heater": power_socket(2000.0) } });
+        let mut after = before.clone();
+        after.rooms[0].devices[0].value = 0.3;
+        assert!(ReportDiff::between(&before, &after, 0.5).is_empty());
+
+        after.rooms[0].devices[0].value = 1.0;
+        let diff = ReportDiff::between(&before, &after, 0.5);
+        let json = diff.to_json().unwrap();
+        assert!(json.contains("\"change\": \"value_changed\""));
+        assert_eq!(serde_json::from_str::<ReportDiff>(&json).unwrap(), diff);
+    }
+}
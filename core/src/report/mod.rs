@@ -1,3 +1,17 @@
+pub mod diff;
+pub mod render;
+
+use crate::aggregate::Aggregates;
+use crate::clock::unix_now;
+use crate::error::SmartHouseError;
+use crate::house::House;
+use crate::labels::Labels;
+use crate::room::Room;
+use crate::smart_device::{DeviceType, LockAuditEntry, SmartDevice};
+use serde::{Deserialize, Serialize};
+use std::io::{self, Stdout, Write};
+use std::str::FromStr;
+
 pub trait Reportable {
     fn generate_report(&self) -> String;
 }
@@ -6,29 +20,418 @@ pub trait Report {
     fn report(&self) -> String;
 }
 
-#[derive(Default)]
-pub struct Reporter {
-    entries: Vec<String>,
+/// Structured snapshot of a house, rendered by [`render::Renderer`]s.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HouseReport {
+    pub rooms: Vec<RoomReport>,
+    pub totals: Aggregates,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RoomReport {
+    pub name: String,
+    pub labels: Labels,
+    pub devices: Vec<DeviceReport>,
+    pub totals: Aggregates,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeviceReport {
+    pub name: String,
+    pub kind: DeviceType,
+    pub state: String,
+    pub value: f64,
+    pub is_on: bool,
+    pub energy: f64,
+    pub labels: Labels,
+    /// Unlocks of a door lock, oldest first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub audit: Vec<LockAuditEntry>,
+}
+
+impl HouseReport {
+    pub fn from_house(house: &House) -> Self {
+        HouseReport {
+            rooms: house
+                .rooms()
+                .map(|(name, room)| RoomReport::from_room(name, room))
+                .collect(),
+            totals: house.aggregates(),
+        }
+    }
+
+    /// Reads a report saved by [`render::JsonRenderer`].
+    pub fn from_json(json: &str) -> Result<HouseReport, SmartHouseError> {
+        serde_json::from_str(json).map_err(|err| SmartHouseError::Storage(err.to_string()))
+    }
 }
 
-impl Reporter {
+impl RoomReport {
+    pub fn from_room(name: &str, room: &Room) -> Self {
+        RoomReport {
+            name: name.to_string(),
+            labels: room.labels().clone(),
+            devices: room
+                .devices()
+                .map(|(name, device)| DeviceReport::from_device(name, device))
+                .collect(),
+            totals: room.aggregates(),
+        }
+    }
+}
+
+impl DeviceReport {
+    pub fn from_device(name: &str, device: &SmartDevice) -> Self {
+        DeviceReport {
+            name: name.to_string(),
+            kind: device.device_type(),
+            state: device.get_state(),
+            value: device.get_value(),
+            is_on: device.is_on(),
+            energy: device.get_energy(),
+            labels: device.labels().clone(),
+            audit: device.audit_trail(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Column {
+    Name,
+    Type,
+    Status,
+    Value,
+    On,
+    Energy,
+    Labels,
+}
+
+impl Column {
+    pub fn title(self) -> &'static str {
+        match self {
+            Column::Name => "Name",
+            Column::Type => "Type",
+            Column::Status => "Status",
+            Column::Value => "Value",
+            Column::On => "On",
+            Column::Energy => "Energy, Wh",
+            Column::Labels => "Labels",
+        }
+    }
+
+    /// Machine-friendly name, used as the CSV header.
+    pub fn key(self) -> &'static str {
+        match self {
+            Column::Name => "device",
+            Column::Type => "type",
+            Column::Status => "state",
+            Column::Value => "value",
+            Column::On => "on",
+            Column::Energy => "energy",
+            Column::Labels => "labels",
+        }
+    }
+
+    fn is_numeric(self) -> bool {
+        matches!(self, Column::Value | Column::Energy)
+    }
+}
+
+impl FromStr for Column {
+    type Err = SmartHouseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "name" | "device" => Ok(Column::Name),
+            "type" => Ok(Column::Type),
+            "status" | "state" => Ok(Column::Status),
+            "value" => Ok(Column::Value),
+            "on" => Ok(Column::On),
+            "energy" => Ok(Column::Energy),
+            "labels" | "tags" => Ok(Column::Labels),
+            _ => Err(SmartHouseError::Unsupported(format!(
+                "report column '{}'",
+                s
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortKey {
+    Name,
+    Type,
+    Status,
+    Value,
+    Energy,
+}
+
+impl FromStr for SortKey {
+    type Err = SmartHouseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.parse::<Column>() {
+            Ok(Column::Name) => Ok(SortKey::Name),
+            Ok(Column::Type) => Ok(SortKey::Type),
+            Ok(Column::Status) => Ok(SortKey::Status),
+            Ok(Column::Value) => Ok(SortKey::Value),
+            Ok(Column::Energy) => Ok(SortKey::Energy),
+            _ => Err(SmartHouseError::Unsupported(format!("sort key '{}'", s))),
+        }
+    }
+}
+
+/// Columns, ordering, filters and widths shared by all renderers.
+/// Without explicit columns every renderer keeps its own.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReportOptions {
+    columns: Option<Vec<Column>>,
+    sort: Option<SortKey>,
+    descending: bool,
+    only_on: bool,
+    room: Option<String>,
+    device_type: Option<DeviceType>,
+    min_width: usize,
+    max_width: Option<usize>,
+}
+
+impl Default for ReportOptions {
+    fn default() -> Self {
+        ReportOptions {
+            columns: None,
+            sort: None,
+            descending: false,
+            only_on: false,
+            room: None,
+            device_type: None,
+            min_width: 13,
+            max_width: None,
+        }
+    }
+}
+
+impl ReportOptions {
     pub fn new() -> Self {
-        println!("{0} Printing report {0}", "=".repeat(30));
+        ReportOptions::default()
+    }
+
+    pub fn columns(mut self, columns: &[Column]) -> Self {
+        self.columns = Some(columns.to_vec());
+        self
+    }
+
+    /// The chosen columns, or `default` if none were chosen.
+    pub(crate) fn columns_or<'a>(&'a self, default: &'a [Column]) -> &'a [Column] {
+        self.columns.as_deref().unwrap_or(default)
+    }
+
+    pub fn sort_by(mut self, key: SortKey) -> Self {
+        self.sort = Some(key);
+        self
+    }
+
+    pub fn descending(mut self) -> Self {
+        self.descending = true;
+        self
+    }
+
+    pub fn only_on(mut self) -> Self {
+        self.only_on = true;
+        self
+    }
+
+    pub fn in_room(mut self, room_name: &str) -> Self {
+        self.room = Some(room_name.to_string());
+        self
+    }
+
+    pub fn of_type(mut self, device_type: DeviceType) -> Self {
+        self.device_type = Some(device_type);
+        self
+    }
+
+    /// Text columns are padded to at least this many characters.
+    pub fn min_width(mut self, width: usize) -> Self {
+        self.min_width = width;
+        self
+    }
+
+    /// Longer cells are cut and end with an ellipsis.
+    pub fn max_width(mut self, width: usize) -> Self {
+        self.max_width = Some(width.max(1));
+        self
+    }
+
+    /// Returns a copy of the report with the filters and the sort order applied,
+    /// and the totals recomputed over the remaining devices.
+    pub fn apply(&self, report: &HouseReport) -> HouseReport {
+        let mut report = report.clone();
+        if let Some(room_name) = &self.room {
+            report.rooms.retain(|room| &room.name == room_name);
+        }
+        for room in &mut report.rooms {
+            *room = self.apply_room(room);
+        }
+        report.totals =
+            Aggregates::from_reports(report.rooms.iter().flat_map(|room| &room.devices));
+        report
+    }
+
+    /// Same as [`ReportOptions::apply`] for the devices of a single room;
+    /// the room filter doesn't apply.
+    pub fn apply_room(&self, room: &RoomReport) -> RoomReport {
+        let mut room = room.clone();
+        room.devices.retain(|device| {
+            (!self.only_on || device.is_on)
+                && self.device_type.is_none_or(|kind| device.kind == kind)
+        });
+        room.totals = Aggregates::from_reports(&room.devices);
+        if let Some(key) = self.sort {
+            room.devices.sort_by(|a, b| {
+                let order = match key {
+                    SortKey::Name => a.name.cmp(&b.name),
+                    SortKey::Type => format!("{:?}", a.kind).cmp(&format!("{:?}", b.kind)),
+                    SortKey::Status => a.state.cmp(&b.state),
+                    SortKey::Value => a.value.total_cmp(&b.value),
+                    SortKey::Energy => a.energy.total_cmp(&b.energy),
+                };
+                match self.descending {
+                    true => order.reverse(),
+                    false => order,
+                }
+            });
+        }
+        room
+    }
+}
+
+/// Collects [`Report`]s and writes them, framed by a header and a footer, to any sink.
+pub struct Reporter<W: Write> {
+    sink: W,
+    header: String,
+    footer: String,
+    timestamps: bool,
+    clock: fn() -> u64,
+    entries: Vec<String>,
+}
+
+impl Reporter<Stdout> {
+    pub fn stdout() -> Self {
+        Reporter::new(io::stdout())
+    }
+}
+
+impl<W: Write> Reporter<W> {
+    pub fn new(sink: W) -> Self {
         Self {
+            sink,
+            header: format!("{0} Printing report {0}", "=".repeat(30)),
+            footer: format!("{0} End  of  report {0}", "=".repeat(30)),
+            timestamps: false,
+            clock: unix_now,
             entries: Vec::new(),
         }
     }
 
+    /// Replaces the banner written first; an empty header is skipped.
+    pub fn with_header(mut self, header: &str) -> Self {
+        self.header = header.to_string();
+        self
+    }
+
+    /// Replaces the banner written last; an empty footer is skipped.
+    pub fn with_footer(mut self, footer: &str) -> Self {
+        self.footer = footer.to_string();
+        self
+    }
+
+    /// Prefixes every entry with the Unix time it was added at.
+    pub fn with_timestamps(mut self) -> Self {
+        self.timestamps = true;
+        self
+    }
+
     #[allow(clippy::should_implement_trait)]
     pub fn add<T: Report>(mut self, item: &T) -> Self {
-        self.entries.push(item.report());
+        let entry = match self.timestamps {
+            true => format!("[{}] {}", (self.clock)(), item.report()),
+            false => item.report(),
+        };
+        self.entries.push(entry);
         self
     }
 
-    pub fn report(self) {
+    /// Writes everything out and hands the sink back.
+    pub fn report(mut self) -> io::Result<W> {
+        if !self.header.is_empty() {
+            writeln!(self.sink, "{}", self.header)?;
+        }
         for entry in &self.entries {
-            println!("{}", entry);
+            writeln!(self.sink, "{}", entry)?;
+        }
+        if !self.footer.is_empty() {
+            writeln!(self.sink, "{}", self.footer)?;
+        }
+        self.sink.flush()?;
+        Ok(self.sink)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Note(&'static str);
+
+    impl Report for Note {
+        fn report(&self) -> String {
+            self.0.to_string()
+        }
+    }
+
+    struct BrokenSink;
+
+    impl Write for BrokenSink {
+        fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+            Err(io::Error::other("disk full"))
         }
-        println!("{0} End  of  report {0}", "=".repeat(30));
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_reporter_writes_to_buffer() {
+        let output = Reporter::new(Vec::new())
+            .with_header("# Status")
+            .with_footer("")
+            .add(&Note("first"))
+            .add(&Note("second"))
+            .report()
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "# Status\nfirst\nsecond\n"
+        );
+    }
+
+    #[test]
+    fn test_reporter_timestamps() {
+        let mut reporter = Reporter::new(Vec::new()).with_header("").with_timestamps();
+        reporter.clock = || 1_700_000_000;
+        let output = reporter.add(&Note("entry")).report().unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.starts_with("[1700000000] entry\n"));
+        assert!(output.ends_with("End  of  report ==============================\n"));
+    }
+
+    #[test]
+    fn test_reporter_returns_write_errors() {
+        let err = Reporter::new(BrokenSink).add(&Note("entry")).report().err();
+        assert_eq!(
+            err.map(|err| err.to_string()),
+            Some("disk full".to_string())
+        );
     }
 }
@@ -0,0 +1,557 @@
+use crate::billing::format_time;
+use crate::error::SmartHouseError;
+use crate::report::{Column, DeviceReport, HouseReport, ReportOptions, RoomReport};
+use std::str::FromStr;
+
+/// Turns a [`HouseReport`] into text in some output format.
+pub trait Renderer {
+    fn render(&self, report: &HouseReport, options: &ReportOptions) -> String;
+}
+
+/// Aligned table, the format of [`crate::report::Reportable`].
+pub struct TextRenderer;
+
+/// The filtered report model; columns and widths don't apply.
+pub struct JsonRenderer;
+
+/// One row per device, with the room name in the first column.
+pub struct CsvRenderer;
+
+/// A section with a device table per room.
+pub struct MarkdownRenderer;
+
+/// A self-contained page, device states colour-coded by CSS classes.
+pub struct HtmlRenderer;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    Text,
+    Json,
+    Csv,
+    Markdown,
+    Html,
+}
+
+impl ReportFormat {
+    pub fn renderer(self) -> Box<dyn Renderer> {
+        match self {
+            ReportFormat::Text => Box::new(TextRenderer),
+            ReportFormat::Json => Box::new(JsonRenderer),
+            ReportFormat::Csv => Box::new(CsvRenderer),
+            ReportFormat::Markdown => Box::new(MarkdownRenderer),
+            ReportFormat::Html => Box::new(HtmlRenderer),
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ReportFormat::Text => "txt",
+            ReportFormat::Json => "json",
+            ReportFormat::Csv => "csv",
+            ReportFormat::Markdown => "md",
+            ReportFormat::Html => "html",
+        }
+    }
+}
+
+impl FromStr for ReportFormat {
+    type Err = SmartHouseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "text" | "txt" => Ok(ReportFormat::Text),
+            "json" => Ok(ReportFormat::Json),
+            "csv" => Ok(ReportFormat::Csv),
+            "md" | "markdown" => Ok(ReportFormat::Markdown),
+            "html" | "htm" => Ok(ReportFormat::Html),
+            _ => Err(SmartHouseError::Unsupported(format!(
+                "report format '{}'",
+                s
+            ))),
+        }
+    }
+}
+
+/// Columns of the plain text table unless the options choose others.
+const TEXT_COLUMNS: [Column; 5] = [
+    Column::Name,
+    Column::Type,
+    Column::Status,
+    Column::Value,
+    Column::Labels,
+];
+
+const CSV_COLUMNS: [Column; 7] = [
+    Column::Name,
+    Column::Type,
+    Column::Status,
+    Column::Value,
+    Column::On,
+    Column::Energy,
+    Column::Labels,
+];
+
+/// Columns of the Markdown and HTML tables unless the options choose others.
+const TABLE_COLUMNS: [Column; 6] = [
+    Column::Name,
+    Column::Type,
+    Column::Status,
+    Column::Value,
+    Column::Energy,
+    Column::Labels,
+];
+
+fn cell(device: &DeviceReport, column: Column) -> String {
+    match column {
+        Column::Name => device.name.clone(),
+        Column::Type => format!("{:?}", device.kind),
+        Column::Status => device.state.clone(),
+        Column::Value => device.value.to_string(),
+        Column::On => device.is_on.to_string(),
+        Column::Energy => format!("{:.3}", device.energy),
+        Column::Labels => device.labels.to_string(),
+    }
+}
+
+impl TextRenderer {
+    pub fn render_room(&self, room: &RoomReport, options: &ReportOptions) -> String {
+        let room = options.apply_room(room);
+        let columns = options.columns_or(&TEXT_COLUMNS);
+        let widths = column_widths(&room.devices.iter().collect::<Vec<_>>(), columns, options);
+        room_table(&room, columns, &widths)
+    }
+
+    /// A single table row without the header.
+    pub fn render_device(&self, device: &DeviceReport, options: &ReportOptions) -> String {
+        let columns = options.columns_or(&TEXT_COLUMNS);
+        let widths = column_widths(&[device], columns, options);
+        text_line(
+            columns.iter().map(|&column| cell(device, column)),
+            columns,
+            &widths,
+        )
+    }
+}
+
+/// Fits every column to its longest cell within the option limits.
+fn column_widths(
+    devices: &[&DeviceReport],
+    columns: &[Column],
+    options: &ReportOptions,
+) -> Vec<usize> {
+    columns
+        .iter()
+        .map(|&column| {
+            let longest = devices
+                .iter()
+                .map(|device| cell(device, column).chars().count())
+                .chain([column.title().len()])
+                .max()
+                .unwrap_or(0);
+            let width = match column.is_numeric() {
+                true => longest.max(6),
+                false => longest.max(options.min_width),
+            };
+            options.max_width.map_or(width, |max| width.min(max))
+        })
+        .collect()
+}
+
+fn fit(text: &str, width: usize, right: bool) -> String {
+    let text = match text.chars().count() > width {
+        true => text.chars().take(width - 1).chain(['…']).collect(),
+        false => text.to_string(),
+    };
+    match right {
+        true => format!("{:>width$}", text, width = width),
+        false => format!("{:width$}", text, width = width),
+    }
+}
+
+fn text_line(cells: impl Iterator<Item = String>, columns: &[Column], widths: &[usize]) -> String {
+    let line: Vec<String> = cells
+        .zip(columns.iter().zip(widths))
+        .map(|(text, (column, &width))| fit(&text, width, column.is_numeric()))
+        .collect();
+    line.join(" ").trim_end().to_string()
+}
+
+fn room_table(room: &RoomReport, columns: &[Column], widths: &[usize]) -> String {
+    let header = text_line(
+        columns.iter().map(|column| column.title().to_string()),
+        columns,
+        widths,
+    );
+    let rows: Vec<String> = room
+        .devices
+        .iter()
+        .map(|device| {
+            text_line(
+                columns.iter().map(|&column| cell(device, column)),
+                columns,
+                widths,
+            )
+        })
+        .collect();
+    let audit: String = audit_lines(room)
+        .iter()
+        .map(|line| format!("\n{}", line))
+        .collect();
+    format!(
+        "{}\n{}\nTotal: {}{}",
+        header,
+        rows.join("\n"),
+        room.totals,
+        audit
+    )
+}
+
+/// One line per unlock of the room's door locks.
+fn audit_lines(room: &RoomReport) -> Vec<String> {
+    room.devices
+        .iter()
+        .flat_map(|device| {
+            device.audit.iter().map(|entry| {
+                format!(
+                    "{} unlocked by {} at {}",
+                    device.name,
+                    entry.user,
+                    format_time(entry.timestamp)
+                )
+            })
+        })
+        .collect()
+}
+
+impl Renderer for TextRenderer {
+    fn render(&self, report: &HouseReport, options: &ReportOptions) -> String {
+        let report = options.apply(report);
+        let columns = options.columns_or(&TEXT_COLUMNS);
+        let devices: Vec<&DeviceReport> =
+            report.rooms.iter().flat_map(|room| &room.devices).collect();
+        let widths = column_widths(&devices, columns, options);
+        let rooms = report
+            .rooms
+            .iter()
+            .map(|room| match room.labels.is_empty() {
+                true => format!(
+                    "Room: {}\n{}\n",
+                    room.name,
+                    room_table(room, columns, &widths)
+                ),
+                false => format!(
+                    "Room: {} ({})\n{}\n",
+                    room.name,
+                    room.labels,
+                    room_table(room, columns, &widths)
+                ),
+            })
+            .collect::<Vec<String>>()
+            .join("\n");
+        format!("{}\nHouse total: {}\n", rooms, report.totals)
+    }
+}
+
+impl Renderer for JsonRenderer {
+    fn render(&self, report: &HouseReport, options: &ReportOptions) -> String {
+        // Reports hold only strings, numbers and string-keyed maps, which always serialize.
+        serde_json::to_string_pretty(&options.apply(report)).expect("report serializes to JSON")
+    }
+}
+
+impl Renderer for CsvRenderer {
+    fn render(&self, report: &HouseReport, options: &ReportOptions) -> String {
+        let columns = options.columns_or(&CSV_COLUMNS);
+        let header: Vec<&str> = columns.iter().map(|column| column.key()).collect();
+        let mut csv = format!("room,{}\n", header.join(","));
+        for room in &options.apply(report).rooms {
+            for device in &room.devices {
+                let fields: Vec<String> = [room.name.clone()]
+                    .into_iter()
+                    .chain(columns.iter().map(|&column| match column {
+                        Column::Energy => device.energy.to_string(),
+                        _ => cell(device, column),
+                    }))
+                    .map(|field| csv_field(&field))
+                    .collect();
+                csv.push_str(&fields.join(","));
+                csv.push('\n');
+            }
+        }
+        csv
+    }
+}
+
+pub(crate) fn csv_field(field: &str) -> String {
+    match field.contains([',', '"', '\n', '\r']) {
+        true => format!("\"{}\"", field.replace('"', "\"\"")),
+        false => field.to_string(),
+    }
+}
+
+impl Renderer for MarkdownRenderer {
+    fn render(&self, report: &HouseReport, options: &ReportOptions) -> String {
+        let report = options.apply(report);
+        let columns = options.columns_or(&TABLE_COLUMNS);
+        let titles: Vec<&str> = columns.iter().map(|c| c.title()).collect();
+        let rules: Vec<&str> = columns
+            .iter()
+            .map(|c| if c.is_numeric() { "---:" } else { "---" })
+            .collect();
+        let mut md = String::from("# House report\n");
+        for room in &report.rooms {
+            md.push_str(&format!("\n## {}\n\n", markdown_cell(&room.name)));
+            if !room.labels.is_empty() {
+                md.push_str(&format!(
+                    "_{}_\n\n",
+                    markdown_cell(&room.labels.to_string())
+                ));
+            }
+            md.push_str(&format!(
+                "| {} |\n|{}|\n",
+                titles.join(" | "),
+                rules.join("|")
+            ));
+            for device in &room.devices {
+                let cells: Vec<String> = columns
+                    .iter()
+                    .map(|&column| match (column, device.is_on) {
+                        (Column::Status, true) => format!("**{}**", device.state),
+                        _ => markdown_cell(&cell(device, column)),
+                    })
+                    .collect();
+                md.push_str(&format!("| {} |\n", cells.join(" | ")));
+            }
+            md.push_str(&format!("\n**Total:** {}\n", room.totals));
+            let audit = audit_lines(room);
+            if !audit.is_empty() {
+                md.push('\n');
+            }
+            for line in audit {
+                md.push_str(&format!("- {}\n", markdown_cell(&line)));
+            }
+        }
+        md.push_str(&format!("\n**House total:** {}\n", report.totals));
+        md
+    }
+}
+
+fn markdown_cell(text: &str) -> String {
+    text.replace('|', "\\|")
+}
+
+const HTML_STYLE: &str = "body { font-family: sans-serif; margin: 2em; }
+table { border-collapse: collapse; margin-bottom: 0.5em; }
+th, td { border: 1px solid #ccc; padding: 0.3em 0.8em; text-align: left; }
+td.on { background: #d4f7d4; }
+td.off { background: #eeeeee; }
+td.alert { background: #f7c6c6; font-weight: bold; }
+.labels, .totals { color: #555; }";
+
+impl Renderer for HtmlRenderer {
+    fn render(&self, report: &HouseReport, options: &ReportOptions) -> String {
+        let report = options.apply(report);
+        let mut html = format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+             <title>House report</title>\n<style>\n{}\n</style>\n</head>\n<body>\n\
+             <h1>House report</h1>\n",
+            HTML_STYLE
+        );
+        let columns = options.columns_or(&TABLE_COLUMNS);
+        let header: String = columns
+            .iter()
+            .map(|column| format!("<th>{}</th>", html_escape(column.title())))
+            .collect();
+        for room in &report.rooms {
+            html.push_str(&format!(
+                "<section>\n<h2>{}</h2>\n",
+                html_escape(&room.name)
+            ));
+            if !room.labels.is_empty() {
+                html.push_str(&format!(
+                    "<p class=\"labels\">{}</p>\n",
+                    html_escape(&room.labels.to_string())
+                ));
+            }
+            html.push_str(&format!("<table>\n<tr>{}</tr>\n", header));
+            for device in &room.devices {
+                let cells: String = columns
+                    .iter()
+                    .map(|&column| match column {
+                        Column::Status => format!(
+                            "<td class=\"{}\">{}</td>",
+                            state_class(device),
+                            html_escape(&device.state)
+                        ),
+                        _ => format!("<td>{}</td>", html_escape(&cell(device, column))),
+                    })
+                    .collect();
+                html.push_str(&format!("<tr>{}</tr>\n", cells));
+            }
+            html.push_str(&format!(
+                "</table>\n<p class=\"totals\">Total: {}</p>\n",
+                room.totals
+            ));
+            let audit = audit_lines(room);
+            if !audit.is_empty() {
+                let items: String = audit
+                    .iter()
+                    .map(|line| format!("<li>{}</li>\n", html_escape(line)))
+                    .collect();
+                html.push_str(&format!("<ul class=\"audit\">\n{}</ul>\n", items));
+            }
+            html.push_str("</section>\n");
+        }
+        html.push_str(&format!(
+            "<p class=\"totals\">House total: {}</p>\n</body>\n</html>\n",
+            report.totals
+        ));
+        html
+    }
+}
+
+fn state_class(device: &DeviceReport) -> &'static str {
+    match (device.state.as_str(), device.is_on) {
+        ("ALARM" | "JAMMED", _) => "alert",
+        (_, true) => "on",
+        (_, false) => "off",
+    }
+}
+
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::house::House;
+    use crate::report::SortKey;
+    use crate::smart_device::{DeviceType, LockAuditEntry, SmartDevice};
+
+    fn report() -> HouseReport {
+        let mut house = House::new();
+        house.add_room("Living, room", None).unwrap();
+        let room = house.get_mut_room("Living, room").unwrap();
+        room.add_device("Lamp", SmartDevice::power_socket(60.0))
+            .unwrap();
+        room.get_mut_device("Lamp").unwrap().turn_on();
+        HouseReport::from_house(&house)
+    }
+
+    fn options() -> ReportOptions {
+        ReportOptions::new()
+    }
+
+    #[test]
+    fn test_csv_renderer() {
+        let csv = CsvRenderer.render(&report(), &options());
+        assert_eq!(
+            csv.lines().collect::<Vec<&str>>(),
+            vec![
+                "room,device,type,state,value,on,energy,labels",
+                "\"Living, room\",Lamp,PowerSocket,ON,60,true,0,",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_json_renderer_round_trip() {
+        let report = report();
+        let json = JsonRenderer.render(&report, &options());
+        let parsed: HouseReport = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, report);
+    }
+
+    #[test]
+    fn test_report_format_from_str() {
+        assert_eq!("CSV".parse::<ReportFormat>(), Ok(ReportFormat::Csv));
+        assert!("xml".parse::<ReportFormat>().is_err());
+    }
+
+    #[test]
+    fn test_markdown_renderer() {
+        let md = MarkdownRenderer.render(&report(), &options());
+        assert!(md.contains("\n## Living, room\n"));
+        assert!(md.contains("| Lamp | PowerSocket | **ON** | 60 | 0.000 |  |\n"));
+        assert!(md.contains("**Total:** 1 of 1 devices on"));
+    }
+
+    #[test]
+    fn test_html_renderer() {
+        let mut report = report();
+        report.rooms[0].name = "<Hall>".to_string();
+        let html = HtmlRenderer.render(&report, &options());
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<h2>&lt;Hall&gt;</h2>"));
+        assert!(html.contains("<td class=\"on\">ON</td>"));
+        assert!(!html.contains("class=\"audit\""));
+    }
+
+    #[test]
+    fn test_renderers_include_audit_trail() {
+        let mut report = report();
+        report.rooms[0].devices[0].audit.push(LockAuditEntry {
+            user: "alice".to_string(),
+            timestamp: 86400 + 3600 + 120,
+        });
+        let line = "Lamp unlocked by alice at 1970-01-02 01:02 UTC";
+        let text = TextRenderer.render(&report, &options());
+        assert!(text.contains(&format!(" Wh\n{}\n", line)));
+        let md = MarkdownRenderer.render(&report, &options());
+        assert!(md.contains(&format!("\n- {}\n", line)));
+        let html = HtmlRenderer.render(&report, &options());
+        assert!(html.contains(&format!("<li>{}</li>", line)));
+    }
+
+    #[test]
+    fn test_text_renderer_options() {
+        let mut house = House::new();
+        house.add_room("Hall", None).unwrap();
+        house.add_room("Kitchen", None).unwrap();
+        let hall = house.get_mut_room("Hall").unwrap();
+        for (name, power) in [("Lamp", 60.0), ("Very long heater name", 2000.0)] {
+            hall.add_device(name, SmartDevice::power_socket(power))
+                .unwrap();
+            hall.get_mut_device(name).unwrap().turn_on();
+        }
+        hall.add_device("Spare", SmartDevice::power_socket(10.0))
+            .unwrap();
+        hall.add_device("Detector", SmartDevice::smoke_detector())
+            .unwrap();
+        let report = HouseReport::from_house(&house);
+
+        let options = ReportOptions::new()
+            .columns(&[Column::Name, Column::Value])
+            .of_type(DeviceType::PowerSocket)
+            .only_on()
+            .in_room("Hall")
+            .sort_by(SortKey::Value)
+            .descending()
+            .min_width(4)
+            .max_width(10);
+        let text = TextRenderer.render(&report, &options);
+        assert_eq!(
+            text.lines().take(4).collect::<Vec<&str>>(),
+            vec![
+                "Room: Hall",
+                "Name        Value",
+                "Very long…   2000",
+                "Lamp           60",
+            ]
+        );
+        assert!(!text.contains("Kitchen"));
+        assert!(text.contains("\nTotal: 2 of 2 devices on, power 2060.0 W"));
+        assert!(text.contains("\nHouse total: 2 of 2 devices on, power 2060.0 W"));
+
+        let room = TextRenderer.render_room(&report.rooms[0], &options);
+        assert_eq!(room.lines().nth(1), Some("Very long…   2000"));
+        assert!(!room.contains("Spare"));
+        let device = TextRenderer.render_device(&report.rooms[0].devices[0], &options);
+        assert_eq!(device, "Lamp     60");
+    }
+}
@@ -1,20 +1,121 @@
-use crate::report::{Report, Reportable};
+use crate::aggregate::Aggregates;
+use crate::config::DeviceConfig;
+use crate::error::SmartHouseError;
+use crate::labels::Labels;
+use crate::report::render::TextRenderer;
+use crate::report::{Report, ReportOptions, Reportable, RoomReport};
 use crate::smart_device::SmartDevice;
-use std::collections::HashMap;
+use indexmap::IndexMap;
 
+/// Declares a room inline. Entries are separated by commas:
+///
+/// - `tags ["a", "b"]` tags the room itself;
+/// - `"Name": kind(args) [on|off] [["tag", ...]]` creates a device, where `kind` is one
+///   of `power_socket`, `thermometer`, `door_lock`, `blind` or `smoke_detector`;
+/// - `"Name": expr` adds an already built `SmartDevice`.
+///
+/// Unknown kinds, states other than `on`/`off` and malformed entries fail to compile.
+/// Duplicate device names panic.
+///
+/// ```
+/// let room = smart_house_lib::room!(tags ["ground"], "Lamp": power_socket(60.0) on);
+/// assert!(room.get_device("Lamp").unwrap().is_on());
+/// ```
+///
+/// ```compile_fail
+/// smart_house_lib::room!("Lamp" power_socket(60.0));
+/// ```
+///
+/// ```compile_fail
+/// smart_house_lib::room!("Lamp": toaster(60.0));
+/// ```
+///
+/// ```compile_fail
+/// smart_house_lib::room!("Lamp": power_socket(60.0) dimmed);
+/// ```
 #[macro_export]
 macro_rules! room {
-    ( $( $key:tt : $device:expr ),* $(,)? ) => {{
-        let mut room = Room::new();
-        $(
-            room.add_device($key, $device);
-        )*
-        Option::Some(room)
+    (@entries $room:ident;) => {};
+    (@entries $room:ident; tags [ $($tag:literal),* $(,)? ] $(, $($rest:tt)*)?) => {
+        $( $room.labels_mut().add_tag($tag); )*
+        $crate::room!(@entries $room; $($($rest)*)?);
+    };
+    (@entries $room:ident;
+        $name:tt : $kind:ident ( $($arg:expr),* $(,)? ) $($state:ident)? $([ $($tag:literal),* $(,)? ])?
+        $(, $($rest:tt)*)?
+    ) => {
+        {
+            #[allow(unused_mut)]
+            let mut device = $crate::room!(@kind $kind ( $($arg),* ));
+            $( $crate::room!(@state device $state); )?
+            $($( device.labels_mut().add_tag($tag); )*)?
+            $room
+                .add_device($name, device)
+                .unwrap_or_else(|err| panic!("room!: {}", err));
+        }
+        $crate::room!(@entries $room; $($($rest)*)?);
+    };
+    (@entries $room:ident; $name:tt : $device:expr $(, $($rest:tt)*)?) => {
+        $room
+            .add_device($name, $device)
+            .unwrap_or_else(|err| panic!("room!: {}", err));
+        $crate::room!(@entries $room; $($($rest)*)?);
+    };
+    (@entries $room:ident; $($other:tt)*) => {
+        compile_error!(concat!("room!: malformed entry: ", stringify!($($other)*)))
+    };
+
+    (@kind power_socket ( $($arg:expr),* )) => {
+        $crate::smart_device::SmartDevice::power_socket($($arg),*)
+    };
+    (@kind thermometer ( $($arg:expr),* )) => {
+        $crate::smart_device::SmartDevice::thermometer($($arg),*)
+    };
+    (@kind door_lock ( $($arg:expr),* )) => {
+        $crate::smart_device::SmartDevice::door_lock($($arg),*)
+    };
+    (@kind blind ( $($arg:expr),* )) => {
+        $crate::smart_device::SmartDevice::blind($($arg),*)
+    };
+    (@kind smoke_detector ( $($arg:expr),* )) => {
+        $crate::smart_device::SmartDevice::smoke_detector($($arg),*)
+    };
+    (@kind $other:ident ( $($arg:expr),* )) => {
+        compile_error!(concat!("room!: unknown device kind `", stringify!($other), "`"))
+    };
+
+    (@state $device:ident on) => {
+        $device.turn_on()
+    };
+    (@state $device:ident off) => {
+        $device.turn_off()
+    };
+    (@state $device:ident $other:ident) => {
+        compile_error!(concat!("room!: expected `on` or `off`, found `", stringify!($other), "`"))
+    };
+
+    ( $($body:tt)* ) => {{
+        #[allow(unused_mut)]
+        let mut room = $crate::room::Room::new();
+        $crate::room!(@entries room; $($body)*);
+        room
     }};
 }
 
+/// Rejects names that the `room/device` syntax of the command line could not address.
+pub(crate) fn check_name(name: &str) -> Result<(), SmartHouseError> {
+    match name.contains('/') {
+        true => Err(SmartHouseError::InvalidParameter(format!(
+            "name '{}' must not contain '/'",
+            name
+        ))),
+        false => Ok(()),
+    }
+}
+
 pub struct Room {
-    devises: HashMap<String, SmartDevice>,
+    devises: IndexMap<String, SmartDevice>,
+    labels: Labels,
 }
 
 impl Default for Room {
@@ -27,9 +128,18 @@ impl Room {
     pub fn new() -> Self {
         Room {
             devises: Default::default(),
+            labels: Labels::new(),
         }
     }
 
+    pub fn labels(&self) -> &Labels {
+        &self.labels
+    }
+
+    pub fn labels_mut(&mut self) -> &mut Labels {
+        &mut self.labels
+    }
+
     pub fn get_device(&self, name: &str) -> Option<&SmartDevice> {
         self.devises.get(name)
     }
@@ -38,33 +148,89 @@ impl Room {
         self.devises.get_mut(name)
     }
 
-    pub fn add_device(&mut self, name: &str, device: SmartDevice) {
+    pub fn add_device(&mut self, name: &str, device: SmartDevice) -> Result<(), SmartHouseError> {
+        check_name(name)?;
+        if self.devises.contains_key(name) {
+            return Err(SmartHouseError::DuplicateName(name.to_string()));
+        }
         self.devises.insert(name.to_string(), device);
+        Ok(())
+    }
+
+    pub fn del_device(&mut self, name: &str) -> Result<SmartDevice, SmartHouseError> {
+        self.devises
+            .shift_remove(name)
+            .ok_or_else(|| SmartHouseError::DeviceNotFound(name.to_string()))
     }
 
-    pub fn del_device(&mut self, name: &str) -> Option<SmartDevice> {
-        self.devises.remove(name)
+    /// Moves a device to `position` in the listing order, clamped to the last slot.
+    pub fn move_device(&mut self, name: &str, position: usize) -> Result<(), SmartHouseError> {
+        let from = self
+            .devises
+            .get_index_of(name)
+            .ok_or_else(|| SmartHouseError::DeviceNotFound(name.to_string()))?;
+        let to = position.min(self.devises.len() - 1);
+        self.devises.move_index(from, to);
+        Ok(())
+    }
+
+    /// Renames a device in place, keeping its state, registry id and position.
+    pub fn rename_device(&mut self, name: &str, new_name: &str) -> Result<(), SmartHouseError> {
+        let index = self
+            .devises
+            .get_index_of(name)
+            .ok_or_else(|| SmartHouseError::DeviceNotFound(name.to_string()))?;
+        if new_name.trim().is_empty() {
+            return Err(SmartHouseError::EmptyName("device".to_string()));
+        }
+        if name == new_name {
+            return Ok(());
+        }
+        check_name(new_name)?;
+        if self.devises.contains_key(new_name) {
+            return Err(SmartHouseError::DuplicateName(new_name.to_string()));
+        }
+        let (_, device) = self.devises.shift_remove_index(index).unwrap();
+        let (new_index, _) = self.devises.insert_full(new_name.to_string(), device);
+        self.devises.move_index(new_index, index);
+        Ok(())
+    }
+
+    /// Creates a copy of the room with fresh devices built from the same parameters.
+    pub fn duplicate(&self) -> Result<Room, SmartHouseError> {
+        let mut room = Room::new();
+        room.labels = self.labels.clone();
+        for (name, device) in &self.devises {
+            let device = DeviceConfig::from_device(name, device).try_create_device()?;
+            room.devises.insert(name.clone(), device);
+        }
+        Ok(room)
+    }
+
+    pub fn devices(&self) -> impl Iterator<Item = (&str, &SmartDevice)> {
+        self.devises
+            .iter()
+            .map(|(name, device)| (name.as_str(), device))
+    }
+
+    pub fn devices_mut(&mut self) -> impl Iterator<Item = (&str, &mut SmartDevice)> {
+        self.devises
+            .iter_mut()
+            .map(|(name, device)| (name.as_str(), device))
     }
 
     pub fn get_devices_names(&self) -> Vec<String> {
         self.devises.keys().cloned().collect()
     }
+
+    pub fn aggregates(&self) -> Aggregates {
+        Aggregates::from_devices(self.devises.values())
+    }
 }
 
 impl Reportable for Room {
     fn generate_report(&self) -> String {
-        format!(
-            "{:14}{:14}{:14}{:>6}\n{}",
-            "Name",
-            "Type",
-            "Status",
-            "Value",
-            self.devises
-                .iter()
-                .map(|(name, device)| { format!("{:14}{}", name, device.generate_report()) })
-                .collect::<Vec<String>>()
-                .join("\n")
-        )
+        TextRenderer.render_room(&RoomReport::from_room("", self), &ReportOptions::default())
     }
 }
 
@@ -85,17 +251,95 @@ mod tests {
     #[test]
     fn test_del_device() {
         let mut room = Room::new();
-        room.add_device("socket", SmartDevice::power_socket(60.0f64));
-        room.del_device("socket");
+        room.add_device("socket", SmartDevice::power_socket(60.0f64))
+            .unwrap();
+        room.del_device("socket").unwrap();
         assert_eq!(room.devises.len(), 0);
     }
 
+    #[test]
+    fn test_add_duplicate_device() {
+        let mut room = Room::new();
+        room.add_device("socket", SmartDevice::power_socket(60.0f64))
+            .unwrap();
+        let result = room.add_device("socket", SmartDevice::power_socket(10.0f64));
+        assert_eq!(
+            result,
+            Err(SmartHouseError::DuplicateName("socket".to_string()))
+        );
+        room.get_mut_device("socket").unwrap().turn_on();
+        assert_eq!(room.get_device("socket").unwrap().get_value(), 60.0);
+    }
+
+    #[test]
+    fn test_devices_keep_insertion_order() {
+        let mut room = Room::new();
+        for name in ["socket3", "socket1", "socket2"] {
+            room.add_device(name, SmartDevice::power_socket(10.0f64))
+                .unwrap();
+        }
+        assert_eq!(
+            room.get_devices_names(),
+            vec!["socket3", "socket1", "socket2"]
+        );
+
+        room.move_device("socket2", 0).unwrap();
+        assert_eq!(
+            room.get_devices_names(),
+            vec!["socket2", "socket3", "socket1"]
+        );
+    }
+
+    #[test]
+    fn test_rename_device_keeps_state_and_position() {
+        let mut room = Room::new();
+        for name in ["socket1", "socket2", "socket3"] {
+            room.add_device(name, SmartDevice::power_socket(10.0f64))
+                .unwrap();
+        }
+        room.get_mut_device("socket2").unwrap().turn_on();
+
+        room.rename_device("socket2", "lamp").unwrap();
+        assert_eq!(room.get_devices_names(), vec!["socket1", "lamp", "socket3"]);
+        assert_eq!(room.get_device("lamp").unwrap().get_value(), 10.0);
+        assert_eq!(
+            room.rename_device("lamp", "socket3"),
+            Err(SmartHouseError::DuplicateName("socket3".to_string()))
+        );
+        assert!(matches!(
+            room.rename_device("lamp", "up/down"),
+            Err(SmartHouseError::InvalidParameter(_))
+        ));
+        assert!(
+            room.add_device("a/b", SmartDevice::power_socket(1.0))
+                .is_err()
+        );
+        assert_eq!(
+            room.rename_device("lamp", ""),
+            Err(SmartHouseError::EmptyName("device".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_duplicate_room_creates_fresh_devices() {
+        let mut room = Room::new();
+        room.add_device("socket", SmartDevice::power_socket(25.0f64))
+            .unwrap();
+        room.get_mut_device("socket").unwrap().turn_on();
+
+        let mut copy = room.duplicate().unwrap();
+        assert_eq!(copy.get_device("socket").unwrap().get_value(), 25.0);
+        copy.get_mut_device("socket").unwrap().turn_off();
+        assert_eq!(room.get_device("socket").unwrap().get_value(), 25.0);
+    }
+
     #[test]
     fn test_del_device_not_exists() {
         let mut room = Room::new();
-        room.add_device("socket1", SmartDevice::power_socket(60.0f64));
+        room.add_device("socket1", SmartDevice::power_socket(60.0f64))
+            .unwrap();
         let result = room.del_device("socket2");
-        assert!(result.is_none());
+        assert!(result.is_err());
         assert!(room.devises.contains_key("socket1"));
     }
 
@@ -106,4 +350,34 @@ mod tests {
         let result = room.get_device("socket");
         assert!(result.is_none());
     }
+
+    #[test]
+    fn test_room_macro() {
+        let room = room! {
+            tags ["ground", "warm"],
+            "Heater": power_socket(2000.0) on ["heating"],
+            "Lamp": power_socket(60.0,) off,
+            "Blind": blind(40.0),
+            "Lock": door_lock(),
+            "Detector": SmartDevice::smoke_detector(),
+        };
+        assert!(room.labels().has_tag("ground") && room.labels().has_tag("warm"));
+        assert_eq!(
+            room.get_devices_names(),
+            vec!["Heater", "Lamp", "Blind", "Lock", "Detector"]
+        );
+
+        let heater = room.get_device("Heater").unwrap();
+        assert!(heater.is_on());
+        assert!(heater.labels().has_tag("heating"));
+        assert!(!room.get_device("Lamp").unwrap().is_on());
+        assert_eq!(room.get_device("Blind").unwrap().get_parameter(), 40.0);
+        assert!(room.get_device("Detector").unwrap().is_detector());
+    }
+
+    #[test]
+    fn test_room_macro_empty() {
+        let room = room!();
+        assert!(room.get_devices_names().is_empty());
+    }
 }
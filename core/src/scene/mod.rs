@@ -0,0 +1,235 @@
+use crate::error::SmartHouseError;
+use crate::house::House;
+use crate::smart_device::{DeviceType, SmartDevice};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Scene {
+    pub name: String,
+    pub entries: Vec<SceneEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SceneEntry {
+    pub room: String,
+    pub device: String,
+    pub value: f64,
+    pub on: bool,
+}
+
+/// A difference between a scene and the current device state.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SceneChange {
+    pub room: String,
+    pub device: String,
+    pub value: Option<(f64, f64)>,
+    pub on: Option<(bool, bool)>,
+    pub result: Result<(), SmartHouseError>,
+}
+
+impl SceneEntry {
+    fn capture(room: &str, device_name: &str, device: &SmartDevice) -> Self {
+        SceneEntry {
+            room: room.to_string(),
+            device: device_name.to_string(),
+            value: device.get_parameter(),
+            on: device.is_on(),
+        }
+    }
+
+    fn diff(&self, device: &SmartDevice) -> SceneChange {
+        let device_type = device.device_type();
+        let value = device.get_parameter();
+        let on = device.is_on();
+        let has_value = matches!(
+            device_type,
+            DeviceType::PowerSocket | DeviceType::Thermometer | DeviceType::Blind
+        );
+        // A blind's state is its position, so only the value is compared.
+        let has_state = device_type != DeviceType::Blind;
+        SceneChange {
+            room: self.room.clone(),
+            device: self.device.clone(),
+            value: (has_value && value != self.value).then_some((value, self.value)),
+            on: (has_state && on != self.on).then_some((on, self.on)),
+            result: Ok(()),
+        }
+    }
+}
+
+impl SceneChange {
+    pub fn is_empty(&self) -> bool {
+        self.value.is_none() && self.on.is_none() && self.result.is_ok()
+    }
+}
+
+impl House {
+    /// Stores the current state of the given devices as a scene, replacing any
+    /// scene with the same name.
+    pub fn capture_scene(
+        &mut self,
+        name: &str,
+        devices: &[(String, String)],
+    ) -> Result<(), SmartHouseError> {
+        let entries = devices
+            .iter()
+            .map(|(room, device_name)| {
+                let device = self.get_device(room, device_name)?;
+                Ok(SceneEntry::capture(room, device_name, device))
+            })
+            .collect::<Result<Vec<_>, SmartHouseError>>()?;
+        self.add_scene(Scene {
+            name: name.to_string(),
+            entries,
+        });
+        Ok(())
+    }
+
+    /// Brings the scene devices to their captured state and returns what changed.
+    /// With `dry_run` nothing is touched and the changes are only listed.
+    pub fn apply_scene(
+        &mut self,
+        name: &str,
+        dry_run: bool,
+    ) -> Result<Vec<SceneChange>, SmartHouseError> {
+        let scene = self
+            .get_scene(name)
+            .ok_or_else(|| SmartHouseError::SceneNotFound(name.to_string()))?
+            .clone();
+        let mut changes = Vec::new();
+        for entry in &scene.entries {
+            let device = self
+                .get_mut_room(&entry.room)
+                .ok_or_else(|| SmartHouseError::RoomNotFound(entry.room.clone()))
+                .and_then(|room| {
+                    room.get_mut_device(&entry.device)
+                        .ok_or_else(|| SmartHouseError::DeviceNotFound(entry.device.clone()))
+                });
+            let change = match device {
+                Ok(device) => {
+                    let mut change = entry.diff(device);
+                    if !dry_run {
+                        change.result = apply_change(device, &change);
+                    }
+                    change
+                }
+                Err(err) => SceneChange {
+                    room: entry.room.clone(),
+                    device: entry.device.clone(),
+                    value: None,
+                    on: None,
+                    result: Err(err),
+                },
+            };
+            if !change.is_empty() {
+                changes.push(change);
+            }
+        }
+        Ok(changes)
+    }
+}
+
+fn apply_change(device: &mut SmartDevice, change: &SceneChange) -> Result<(), SmartHouseError> {
+    if let Some((_, value)) = change.value
+        && !device.set_value(value)
+    {
+        return Err(SmartHouseError::Unsupported(format!(
+            "{:?} does not accept value {}",
+            device.device_type(),
+            value
+        )));
+    }
+    match change.on {
+        Some((_, on)) => device.switch(on),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scene_house() -> House {
+        let mut house = House::new();
+        house.add_room("Hall", None).unwrap();
+        let hall = house.get_mut_room("Hall").unwrap();
+        hall.add_device("Lamp", SmartDevice::power_socket(60.0))
+            .unwrap();
+        hall.add_device("Heater", SmartDevice::power_socket(1000.0))
+            .unwrap();
+        hall.add_device("Blind", SmartDevice::blind(100.0)).unwrap();
+        house
+    }
+
+    fn devices(house: &House) -> Vec<(String, String)> {
+        house
+            .devices()
+            .map(|(room, name, _)| (room.to_string(), name.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_apply_scene_restores_state() {
+        let mut house = scene_house();
+        house
+            .get_mut_room("Hall")
+            .unwrap()
+            .get_mut_device("Lamp")
+            .unwrap()
+            .turn_on();
+        house.capture_scene("Evening", &devices(&house)).unwrap();
+
+        let hall = house.get_mut_room("Hall").unwrap();
+        hall.get_mut_device("Lamp").unwrap().turn_off();
+        hall.get_mut_device("Heater").unwrap().set_value(2000.0);
+
+        let changes = house.apply_scene("Evening", false).unwrap();
+        assert_eq!(changes.len(), 2);
+        assert!(changes.iter().all(|change| change.result.is_ok()));
+        assert_eq!(house.get_device("Hall", "Lamp").unwrap().get_value(), 60.0);
+        assert_eq!(
+            house.get_device("Hall", "Heater").unwrap().get_parameter(),
+            1000.0
+        );
+        assert!(house.apply_scene("Evening", false).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_dry_run_lists_changes_without_applying() {
+        let mut house = scene_house();
+        house.capture_scene("Night", &devices(&house)).unwrap();
+        house
+            .get_mut_room("Hall")
+            .unwrap()
+            .get_mut_device("Lamp")
+            .unwrap()
+            .turn_on();
+
+        let changes = house.apply_scene("Night", true).unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].device, "Lamp");
+        assert_eq!(changes[0].on, Some((true, false)));
+        assert!(house.get_device("Hall", "Lamp").unwrap().is_on());
+    }
+
+    #[test]
+    fn test_scene_reports_missing_devices() {
+        let mut house = scene_house();
+        house.capture_scene("Away", &devices(&house)).unwrap();
+        house
+            .get_mut_room("Hall")
+            .unwrap()
+            .del_device("Lamp")
+            .unwrap();
+
+        let changes = house.apply_scene("Away", false).unwrap();
+        assert_eq!(
+            changes[0].result,
+            Err(SmartHouseError::DeviceNotFound("Lamp".to_string()))
+        );
+        assert!(matches!(
+            house.apply_scene("Party", true),
+            Err(SmartHouseError::SceneNotFound(_))
+        ));
+    }
+}
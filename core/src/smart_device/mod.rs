@@ -1,8 +1,16 @@
-use crate::report::Reportable;
+use crate::clock::unix_now;
+use crate::error::SmartHouseError;
+use crate::labels::Labels;
+use crate::report::render::TextRenderer;
+use crate::report::{Column, DeviceReport, ReportOptions, Reportable};
 use libloading::{Library, Symbol};
-use std::ffi::c_uint;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::ffi::{CString, c_uint};
 use std::fmt::{self, Debug, Formatter};
 use std::os::raw::c_char;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
 #[cfg(target_os = "macos")]
 const LIB_EXTENSION: &str = "dylib";
@@ -11,20 +19,80 @@ const LIB_EXTENSION: &str = "so";
 #[cfg(target_os = "windows")]
 const LIB_EXTENSION: &str = "dll";
 
-fn lib_path() -> String {
-    format!("./target/debug/libdevices.{}", LIB_EXTENSION)
+/// The devices library next to the running binary (or its parent, for test binaries in
+/// `deps`), falling back to the build directory relative to the working directory.
+fn lib_path() -> PathBuf {
+    let file = format!("libdevices.{}", LIB_EXTENSION);
+    let exe_dir = env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(Path::to_path_buf));
+    exe_dir
+        .iter()
+        .flat_map(|dir| [dir.join(&file), dir.join("..").join(&file)])
+        .find(|path| path.exists())
+        .unwrap_or_else(|| Path::new("./target/debug").join(file))
 }
 
 #[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum DeviceType {
     PowerSocket,
     Thermometer,
+    DoorLock,
+    Blind,
+    SmokeDetector,
+}
+
+impl DeviceType {
+    pub const ALL: [DeviceType; 5] = [
+        DeviceType::PowerSocket,
+        DeviceType::Thermometer,
+        DeviceType::DoorLock,
+        DeviceType::Blind,
+        DeviceType::SmokeDetector,
+    ];
+}
+
+/// Accepts `PowerSocket`, `power_socket` and `power-socket` alike.
+impl FromStr for DeviceType {
+    type Err = SmartHouseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let key: String = s
+            .chars()
+            .filter(|c| !matches!(c, '_' | '-' | ' '))
+            .collect();
+        DeviceType::ALL
+            .into_iter()
+            .find(|kind| format!("{:?}", kind).eq_ignore_ascii_case(&key))
+            .ok_or_else(|| SmartHouseError::InvalidParameter(format!("device type '{}'", s)))
+    }
+}
+
+#[repr(C)]
+struct DeviceContext {
+    device_type: DeviceType,
+    value: f64,
+    is_on: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PinCode {
+    pub user: String,
+    pub pin: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LockAuditEntry {
+    pub user: String,
+    pub timestamp: u64,
 }
 
 pub struct SmartDevice {
     device_id: c_uint,
     device_type: DeviceType,
     lib: Option<Library>,
+    labels: Labels,
 }
 
 unsafe extern "C" {
@@ -32,14 +100,48 @@ unsafe extern "C" {
     fn device_on(id: c_uint) -> bool;
     fn device_off(id: c_uint) -> bool;
     fn device_get_value(id: c_uint) -> f64;
+    fn device_set_value(id: c_uint, value: f64) -> bool;
+    fn device_get_energy(id: c_uint) -> f64;
+    fn device_get_context(id: c_uint) -> DeviceContext;
     fn device_get_name(id: c_uint, buffer: *mut c_char, len: usize) -> usize;
     fn device_state(id: c_uint, buffer: *mut c_char, len: usize) -> usize;
+    fn lock_add_code(id: c_uint, user: *const c_char, pin: *const c_char) -> bool;
+    fn lock_remove_code(id: c_uint, pin: *const c_char) -> bool;
+    fn lock_code_count(id: c_uint) -> usize;
+    fn lock_code_user(id: c_uint, index: usize, buffer: *mut c_char, len: usize) -> usize;
+    fn lock_code_pin(id: c_uint, index: usize, buffer: *mut c_char, len: usize) -> usize;
+    fn lock_lock(id: c_uint) -> bool;
+    fn lock_unlock(id: c_uint, pin: *const c_char, timestamp: u64) -> bool;
+    fn lock_set_jammed(id: c_uint, jammed: bool) -> bool;
+    fn lock_is_jammed(id: c_uint) -> bool;
+    fn lock_push_audit(id: c_uint, user: *const c_char, timestamp: u64) -> bool;
+    fn lock_audit_len(id: c_uint) -> usize;
+    fn lock_audit_user(id: c_uint, index: usize, buffer: *mut c_char, len: usize) -> usize;
+    fn lock_audit_time(id: c_uint, index: usize) -> u64;
+    fn devices_tick(seconds: f64);
+    fn blind_set_target(id: c_uint, position: f64) -> bool;
+    fn blind_get_target(id: c_uint) -> f64;
+    fn blind_stop(id: c_uint) -> bool;
+    fn blind_settle(id: c_uint) -> bool;
+    fn blind_get_speed(id: c_uint) -> f64;
+    fn blind_set_speed(id: c_uint, speed: f64) -> bool;
+    fn detector_trigger(id: c_uint) -> bool;
+    fn detector_clear(id: c_uint) -> bool;
+    fn detector_is_alarm(id: c_uint) -> bool;
+}
+
+/// Advances the simulated clock of all statically linked devices.
+pub fn tick(seconds: f64) {
+    unsafe { devices_tick(seconds) }
 }
 
 type NewDevice = unsafe extern "C" fn(device_type: DeviceType, value: f64) -> c_uint;
 type DeviceOn = unsafe extern "C" fn(id: c_uint) -> bool;
 type DeviceOff = unsafe extern "C" fn(id: c_uint) -> bool;
 type DeviceGetValue = unsafe extern "C" fn(id: c_uint) -> f64;
+type DeviceSetValue = unsafe extern "C" fn(id: c_uint, value: f64) -> bool;
+type DeviceGetEnergy = unsafe extern "C" fn(id: c_uint) -> f64;
+type DeviceGetContext = unsafe extern "C" fn(id: c_uint) -> DeviceContext;
 type DeviceGetName = unsafe extern "C" fn(id: c_uint, buffer: *mut c_char, len: usize) -> usize;
 type DeviceState = unsafe extern "C" fn(id: c_uint, buffer: *mut c_char, len: usize) -> usize;
 
@@ -49,15 +151,23 @@ impl SmartDevice {
             device_id,
             device_type,
             lib,
+            labels: Labels::new(),
         }
     }
 
+    /// Panics if the thermometer backend can't be loaded, see [`SmartDevice::try_thermometer`].
     pub fn thermometer(temperature: f64) -> Self {
+        Self::try_thermometer(temperature).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    pub fn try_thermometer(temperature: f64) -> Result<Self, SmartHouseError> {
+        let backend_error = |err: libloading::Error| SmartHouseError::Backend(err.to_string());
         unsafe {
-            let lib = Library::new(lib_path()).unwrap();
-            let new_device: Symbol<'_, NewDevice> = lib.get(b"new_device").unwrap();
+            let lib = Library::new(lib_path()).map_err(backend_error)?;
+            let new_device: Symbol<'_, NewDevice> =
+                lib.get(b"new_device").map_err(backend_error)?;
             let id = new_device(DeviceType::Thermometer, temperature);
-            Self::new(id, DeviceType::Thermometer, Some(lib))
+            Ok(Self::new(id, DeviceType::Thermometer, Some(lib)))
         }
     }
 
@@ -66,6 +176,21 @@ impl SmartDevice {
         Self::new(id, DeviceType::PowerSocket, None)
     }
 
+    pub fn door_lock() -> Self {
+        let id = unsafe { new_device(DeviceType::DoorLock, 0.0) };
+        Self::new(id, DeviceType::DoorLock, None)
+    }
+
+    pub fn blind(position: f64) -> Self {
+        let id = unsafe { new_device(DeviceType::Blind, position) };
+        Self::new(id, DeviceType::Blind, None)
+    }
+
+    pub fn smoke_detector() -> Self {
+        let id = unsafe { new_device(DeviceType::SmokeDetector, 0.0) };
+        Self::new(id, DeviceType::SmokeDetector, None)
+    }
+
     fn call_device_on(&self) -> bool {
         match self.device_type {
             DeviceType::Thermometer => unsafe {
@@ -73,7 +198,10 @@ impl SmartDevice {
                 let func: Symbol<'_, DeviceOn> = lib.get(b"device_on").unwrap();
                 func(self.device_id)
             },
-            DeviceType::PowerSocket => unsafe { device_on(self.device_id) },
+            DeviceType::PowerSocket
+            | DeviceType::DoorLock
+            | DeviceType::Blind
+            | DeviceType::SmokeDetector => unsafe { device_on(self.device_id) },
         }
     }
 
@@ -84,7 +212,10 @@ impl SmartDevice {
                 let func: Symbol<'_, DeviceOff> = lib.get(b"device_off").unwrap();
                 func(self.device_id)
             },
-            DeviceType::PowerSocket => unsafe { device_off(self.device_id) },
+            DeviceType::PowerSocket
+            | DeviceType::DoorLock
+            | DeviceType::Blind
+            | DeviceType::SmokeDetector => unsafe { device_off(self.device_id) },
         }
     }
 
@@ -95,7 +226,52 @@ impl SmartDevice {
                 let func: Symbol<'_, DeviceGetValue> = lib.get(b"device_get_value").unwrap();
                 func(self.device_id)
             },
-            DeviceType::PowerSocket => unsafe { device_get_value(self.device_id) },
+            DeviceType::PowerSocket
+            | DeviceType::DoorLock
+            | DeviceType::Blind
+            | DeviceType::SmokeDetector => unsafe { device_get_value(self.device_id) },
+        }
+    }
+
+    fn call_device_set_value(&self, value: f64) -> bool {
+        match self.device_type {
+            DeviceType::Thermometer => unsafe {
+                let lib = self.lib.as_ref().unwrap();
+                let func: Symbol<'_, DeviceSetValue> = lib.get(b"device_set_value").unwrap();
+                func(self.device_id, value)
+            },
+            DeviceType::PowerSocket
+            | DeviceType::DoorLock
+            | DeviceType::Blind
+            | DeviceType::SmokeDetector => unsafe { device_set_value(self.device_id, value) },
+        }
+    }
+
+    fn call_device_get_energy(&self) -> f64 {
+        match self.device_type {
+            DeviceType::Thermometer => unsafe {
+                let lib = self.lib.as_ref().unwrap();
+                let func: Symbol<'_, DeviceGetEnergy> = lib.get(b"device_get_energy").unwrap();
+                func(self.device_id)
+            },
+            DeviceType::PowerSocket
+            | DeviceType::DoorLock
+            | DeviceType::Blind
+            | DeviceType::SmokeDetector => unsafe { device_get_energy(self.device_id) },
+        }
+    }
+
+    fn call_device_get_context(&self) -> DeviceContext {
+        match self.device_type {
+            DeviceType::Thermometer => unsafe {
+                let lib = self.lib.as_ref().unwrap();
+                let func: Symbol<'_, DeviceGetContext> = lib.get(b"device_get_context").unwrap();
+                func(self.device_id)
+            },
+            DeviceType::PowerSocket
+            | DeviceType::DoorLock
+            | DeviceType::Blind
+            | DeviceType::SmokeDetector => unsafe { device_get_context(self.device_id) },
         }
     }
 
@@ -107,7 +283,10 @@ impl SmartDevice {
                 let func: Symbol<'_, DeviceGetName> = lib.get(b"device_get_name").unwrap();
                 func(self.device_id, buffer.as_mut_ptr(), buffer.len())
             },
-            DeviceType::PowerSocket => unsafe {
+            DeviceType::PowerSocket
+            | DeviceType::DoorLock
+            | DeviceType::Blind
+            | DeviceType::SmokeDetector => unsafe {
                 device_get_name(self.device_id, buffer.as_mut_ptr(), buffer.len())
             },
         };
@@ -124,7 +303,10 @@ impl SmartDevice {
                 let func: Symbol<'_, DeviceState> = lib.get(b"device_state").unwrap();
                 func(self.device_id, buffer.as_mut_ptr(), buffer.len())
             },
-            DeviceType::PowerSocket => unsafe {
+            DeviceType::PowerSocket
+            | DeviceType::DoorLock
+            | DeviceType::Blind
+            | DeviceType::SmokeDetector => unsafe {
                 device_state(self.device_id, buffer.as_mut_ptr(), buffer.len())
             },
         };
@@ -141,19 +323,213 @@ impl SmartDevice {
         self.call_device_off();
     }
 
+    /// Turns the device on or off, failing where the device cannot follow:
+    /// a jammed door lock, or unlocking a door without a PIN.
+    pub fn switch(&mut self, on: bool) -> Result<(), SmartHouseError> {
+        match (self.device_type, on) {
+            (DeviceType::DoorLock, true) => match self.lock() {
+                true => Ok(()),
+                false => Err(SmartHouseError::Unsupported(
+                    "door lock is jammed".to_string(),
+                )),
+            },
+            (DeviceType::DoorLock, false) => Err(SmartHouseError::Unsupported(
+                "door lock needs a PIN to unlock".to_string(),
+            )),
+            (_, true) => {
+                self.turn_on();
+                Ok(())
+            }
+            (_, false) => {
+                self.turn_off();
+                Ok(())
+            }
+        }
+    }
+
     pub fn get_value(&self) -> f64 {
         self.call_device_get_value()
     }
 
+    /// Energy consumed in simulated time, in watt-hours.
+    pub fn get_energy(&self) -> f64 {
+        self.call_device_get_energy()
+    }
+
+    /// Changes the device parameter: wattage, temperature or blind target position.
+    pub fn set_value(&mut self, value: f64) -> bool {
+        self.call_device_set_value(value)
+    }
+
     fn get_name(&self) -> String {
         self.call_device_get_name()
     }
 
-    fn get_state(&self) -> String {
+    pub fn device_type(&self) -> DeviceType {
+        self.device_type
+    }
+
+    pub fn labels(&self) -> &Labels {
+        &self.labels
+    }
+
+    pub fn labels_mut(&mut self) -> &mut Labels {
+        &mut self.labels
+    }
+
+    /// Returns the value the device was configured with, regardless of its state.
+    pub fn get_parameter(&self) -> f64 {
+        self.call_device_get_context().value
+    }
+
+    pub fn is_on(&self) -> bool {
+        self.call_device_get_context().is_on
+    }
+
+    pub fn is_lock(&self) -> bool {
+        matches!(self.device_type, DeviceType::DoorLock)
+    }
+
+    pub fn add_code(&mut self, user: &str, pin: &str) -> bool {
+        let (Ok(user), Ok(pin)) = (CString::new(user), CString::new(pin)) else {
+            return false;
+        };
+        unsafe { lock_add_code(self.device_id, user.as_ptr(), pin.as_ptr()) }
+    }
+
+    pub fn remove_code(&mut self, pin: &str) -> bool {
+        let Ok(pin) = CString::new(pin) else {
+            return false;
+        };
+        unsafe { lock_remove_code(self.device_id, pin.as_ptr()) }
+    }
+
+    pub fn lock(&mut self) -> bool {
+        unsafe { lock_lock(self.device_id) }
+    }
+
+    pub fn unlock(&mut self, pin: &str) -> bool {
+        let Ok(pin) = CString::new(pin) else {
+            return false;
+        };
+        unsafe { lock_unlock(self.device_id, pin.as_ptr(), unix_now()) }
+    }
+
+    pub fn set_jammed(&mut self, jammed: bool) -> bool {
+        unsafe { lock_set_jammed(self.device_id, jammed) }
+    }
+
+    pub fn is_jammed(&self) -> bool {
+        unsafe { lock_is_jammed(self.device_id) }
+    }
+
+    pub fn is_blind(&self) -> bool {
+        matches!(self.device_type, DeviceType::Blind)
+    }
+
+    pub fn open(&mut self) -> bool {
+        self.set_position(100.0)
+    }
+
+    pub fn close(&mut self) -> bool {
+        self.set_position(0.0)
+    }
+
+    pub fn stop(&mut self) -> bool {
+        unsafe { blind_stop(self.device_id) }
+    }
+
+    pub fn set_position(&mut self, position: f64) -> bool {
+        unsafe { blind_set_target(self.device_id, position) }
+    }
+
+    pub fn target_position(&self) -> f64 {
+        unsafe { blind_get_target(self.device_id) }
+    }
+
+    /// Moves a blind straight to its target position.
+    pub fn settle(&mut self) -> bool {
+        unsafe { blind_settle(self.device_id) }
+    }
+
+    pub fn speed(&self) -> f64 {
+        unsafe { blind_get_speed(self.device_id) }
+    }
+
+    pub fn set_speed(&mut self, speed: f64) -> bool {
+        unsafe { blind_set_speed(self.device_id, speed) }
+    }
+
+    pub fn is_detector(&self) -> bool {
+        matches!(self.device_type, DeviceType::SmokeDetector)
+    }
+
+    /// Simulates smoke or CO reaching the detector.
+    pub fn trigger_alarm(&mut self) -> bool {
+        unsafe { detector_trigger(self.device_id) }
+    }
+
+    pub fn clear_alarm(&mut self) -> bool {
+        unsafe { detector_clear(self.device_id) }
+    }
+
+    pub fn is_alarm(&self) -> bool {
+        unsafe { detector_is_alarm(self.device_id) }
+    }
+
+    pub fn pin_codes(&self) -> Vec<PinCode> {
+        let len = unsafe { lock_code_count(self.device_id) };
+        (0..len)
+            .map(|index| PinCode {
+                user: read_c_string(|buffer, len| unsafe {
+                    lock_code_user(self.device_id, index, buffer, len)
+                }),
+                pin: read_c_string(|buffer, len| unsafe {
+                    lock_code_pin(self.device_id, index, buffer, len)
+                }),
+            })
+            .collect()
+    }
+
+    pub fn audit_trail(&self) -> Vec<LockAuditEntry> {
+        let len = unsafe { lock_audit_len(self.device_id) };
+        (0..len)
+            .map(|index| LockAuditEntry {
+                user: read_c_string(|buffer, len| unsafe {
+                    lock_audit_user(self.device_id, index, buffer, len)
+                }),
+                timestamp: unsafe { lock_audit_time(self.device_id, index) },
+            })
+            .collect()
+    }
+
+    /// Appends an entry to a lock's audit trail, e.g. one restored from a saved house.
+    pub fn push_audit_entry(&mut self, entry: &LockAuditEntry) -> bool {
+        let Ok(user) = CString::new(entry.user.as_str()) else {
+            return false;
+        };
+        unsafe { lock_push_audit(self.device_id, user.as_ptr(), entry.timestamp) }
+    }
+
+    pub fn get_state(&self) -> String {
         self.call_device_state()
     }
 }
 
+/// Reads a string through the devices ABI, which returns the full length of the string,
+/// retrying with a larger buffer when the first one was too short.
+fn read_c_string(read: impl Fn(*mut c_char, usize) -> usize) -> String {
+    let mut buffer = vec![0 as c_char; 32];
+    let mut written = read(buffer.as_mut_ptr(), buffer.len());
+    if written >= buffer.len() {
+        buffer = vec![0 as c_char; written + 1];
+        written = read(buffer.as_mut_ptr(), buffer.len());
+    }
+    let slice = &buffer[..written.min(buffer.len() - 1)];
+    let bytes: Vec<u8> = slice.iter().map(|&b| b as u8).collect();
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
 impl Debug for SmartDevice {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         write!(
@@ -168,12 +544,8 @@ impl Debug for SmartDevice {
 
 impl Reportable for SmartDevice {
     fn generate_report(&self) -> String {
-        format!(
-            "{:14}{:14}{:>6}",
-            self.get_name(),
-            self.get_state(),
-            self.get_value()
-        )
+        let options = ReportOptions::new().columns(&[Column::Name, Column::Status, Column::Value]);
+        TextRenderer.render_device(&DeviceReport::from_device(&self.get_name(), self), &options)
     }
 }
 
@@ -189,6 +561,13 @@ mod tests {
         assert_eq!(device.get_state(), "OFF".to_string());
     }
 
+    #[test]
+    fn test_device_type_from_str() {
+        assert_eq!("power-socket".parse(), Ok(DeviceType::PowerSocket));
+        assert_eq!("SmokeDetector".parse(), Ok(DeviceType::SmokeDetector));
+        assert!("kettle".parse::<DeviceType>().is_err());
+    }
+
     #[test]
     fn test_turn_on_off_power_socket() {
         let mut device = SmartDevice::power_socket(120.0);
@@ -201,4 +580,54 @@ mod tests {
         assert_eq!(device.get_state(), "OFF".to_ascii_uppercase());
         assert_eq!(device.get_value(), 0.0);
     }
+
+    #[test]
+    fn test_door_lock_pin_and_audit() {
+        let mut device = SmartDevice::door_lock();
+        assert!(device.is_lock());
+        assert!(device.add_code("alice", "1234"));
+
+        assert!(device.lock());
+        assert_eq!(device.get_state(), "LOCKED".to_string());
+        assert!(!device.unlock("4321"));
+        assert!(device.unlock("1234"));
+        assert_eq!(device.get_state(), "UNLOCKED".to_string());
+
+        let audit = device.audit_trail();
+        assert_eq!(audit.len(), 1);
+        assert_eq!(audit[0].user, "alice");
+
+        let user = "a".repeat(40);
+        let pin = "9".repeat(64);
+        assert!(device.add_code(&user, &pin));
+        let codes = device.pin_codes();
+        assert_eq!(codes[1].user, user);
+        assert_eq!(codes[1].pin, pin);
+    }
+
+    #[test]
+    fn test_blind_moves_to_target() {
+        let mut device = SmartDevice::blind(0.0);
+        assert!(device.is_blind());
+        assert_eq!(device.get_state(), "CLOSED".to_string());
+
+        assert!(device.set_position(50.0));
+        assert_eq!(device.target_position(), 50.0);
+        assert_eq!(device.get_state(), "OPENING".to_string());
+
+        assert!(device.set_speed(5.0));
+        assert_eq!(device.speed(), 5.0);
+        assert!(device.settle());
+        assert_eq!(device.get_value(), 50.0);
+        assert_eq!(device.get_state(), "OPEN".to_string());
+        assert!(!device.set_position(150.0));
+    }
+
+    #[test]
+    fn test_lock_calls_on_power_socket_fail() {
+        let mut device = SmartDevice::power_socket(10.0);
+        assert!(!device.add_code("alice", "1234"));
+        assert!(!device.lock());
+        assert!(device.audit_trail().is_empty());
+    }
 }
@@ -0,0 +1,148 @@
+use crate::device::Device;
+use crate::{DeviceContext, DeviceType};
+
+const DEFAULT_SPEED: f64 = 10.0;
+
+#[derive(Debug)]
+pub struct Blind {
+    position: f64,
+    target: f64,
+    speed: f64,
+}
+
+impl Blind {
+    pub fn set_target(&mut self, target: f64) -> bool {
+        if !(0.0..=100.0).contains(&target) {
+            return false;
+        }
+        self.target = target;
+        true
+    }
+
+    pub fn get_target(&self) -> f64 {
+        self.target
+    }
+
+    pub fn stop(&mut self) {
+        self.target = self.position;
+    }
+
+    /// Moves the blind straight to its target, skipping the travel time.
+    pub fn settle(&mut self) {
+        self.position = self.target;
+    }
+
+    pub fn get_speed(&self) -> f64 {
+        self.speed
+    }
+
+    pub fn set_speed(&mut self, speed: f64) -> bool {
+        if !speed.is_finite() || speed <= 0.0 {
+            return false;
+        }
+        self.speed = speed;
+        true
+    }
+}
+
+impl Device for Blind {
+    fn new(position: f64) -> Self {
+        let position = position.clamp(0.0, 100.0);
+        Self {
+            position,
+            target: position,
+            speed: DEFAULT_SPEED,
+        }
+    }
+
+    fn get_value(&self) -> f64 {
+        self.position
+    }
+
+    fn get_name(&self) -> String {
+        String::from("Blind")
+    }
+
+    fn get_state(&self) -> String {
+        if self.target > self.position {
+            "OPENING"
+        } else if self.target < self.position {
+            "CLOSING"
+        } else if self.position == 0.0 {
+            "CLOSED"
+        } else {
+            "OPEN"
+        }
+        .to_string()
+    }
+
+    fn on(&mut self) {
+        self.target = 100.0;
+    }
+
+    fn off(&mut self) {
+        self.target = 0.0;
+    }
+
+    fn set_value(&mut self, target: f64) -> bool {
+        self.set_target(target)
+    }
+
+    fn tick(&mut self, seconds: f64) {
+        let step = self.speed * seconds.max(0.0);
+        let distance = self.target - self.position;
+        if distance.abs() <= step {
+            self.position = self.target;
+        } else {
+            self.position += step.copysign(distance);
+        }
+    }
+
+    fn get_context(&self) -> DeviceContext {
+        DeviceContext {
+            device_type: DeviceType::Blind,
+            value: self.position,
+            is_on: self.position > 0.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blind_moves_over_time() {
+        let mut blind = Blind::new(0.0);
+        assert_eq!(blind.get_state(), "CLOSED".to_string());
+
+        blind.on();
+        assert_eq!(blind.get_state(), "OPENING".to_string());
+        blind.tick(2.0);
+        assert_eq!(blind.get_value(), 20.0);
+
+        blind.tick(100.0);
+        assert_eq!(blind.get_value(), 100.0);
+        assert_eq!(blind.get_state(), "OPEN".to_string());
+    }
+
+    #[test]
+    fn test_blind_stop_and_speed() {
+        let mut blind = Blind::new(100.0);
+        assert!(blind.set_speed(25.0));
+        assert!(blind.set_target(0.0));
+        blind.tick(1.0);
+        blind.stop();
+        blind.tick(1.0);
+        assert_eq!(blind.get_value(), 75.0);
+        assert_eq!(blind.get_state(), "OPEN".to_string());
+
+        assert!(blind.set_target(10.0));
+        blind.settle();
+        assert_eq!(blind.get_value(), 10.0);
+        assert_eq!(blind.get_state(), "OPEN".to_string());
+
+        assert!(!blind.set_target(120.0));
+        assert!(!blind.set_speed(0.0));
+    }
+}
@@ -1,4 +1,7 @@
-pub trait Device: Send + Sync {
+use crate::DeviceContext;
+use std::any::Any;
+
+pub trait Device: Any + Send + Sync {
     fn new(value: f64) -> Self
     where
         Self: Sized;
@@ -11,4 +14,16 @@ pub trait Device: Send + Sync {
     fn on(&mut self);
 
     fn off(&mut self);
+
+    fn get_context(&self) -> DeviceContext;
+
+    fn set_value(&mut self, _value: f64) -> bool {
+        false
+    }
+
+    fn get_energy(&self) -> f64 {
+        0.0
+    }
+
+    fn tick(&mut self, _seconds: f64) {}
 }
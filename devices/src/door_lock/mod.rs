@@ -0,0 +1,182 @@
+use crate::device::Device;
+use crate::{DeviceContext, DeviceType};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockState {
+    Locked,
+    Unlocked,
+    Jammed,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuditEntry {
+    pub user: String,
+    pub timestamp: u64,
+}
+
+#[derive(Debug)]
+pub struct DoorLock {
+    state: LockState,
+    codes: BTreeMap<String, String>,
+    audit: Vec<AuditEntry>,
+}
+
+impl DoorLock {
+    pub fn add_code(&mut self, user: &str, pin: &str) -> bool {
+        if user.is_empty() || pin.is_empty() || self.codes.contains_key(pin) {
+            return false;
+        }
+        self.codes.insert(pin.to_string(), user.to_string());
+        true
+    }
+
+    pub fn remove_code(&mut self, pin: &str) -> bool {
+        self.codes.remove(pin).is_some()
+    }
+
+    /// Returns `(user, pin)` pairs ordered by PIN.
+    pub fn codes(&self) -> Vec<(&str, &str)> {
+        self.codes
+            .iter()
+            .map(|(pin, user)| (user.as_str(), pin.as_str()))
+            .collect()
+    }
+
+    pub fn lock(&mut self) -> bool {
+        if self.state == LockState::Jammed {
+            return false;
+        }
+        self.state = LockState::Locked;
+        true
+    }
+
+    /// Unlocks with a known PIN, recording the user at `timestamp` in Unix seconds.
+    pub fn unlock(&mut self, pin: &str, timestamp: u64) -> bool {
+        if self.state == LockState::Jammed {
+            return false;
+        }
+        let Some(user) = self.codes.get(pin) else {
+            return false;
+        };
+        self.audit.push(AuditEntry {
+            user: user.clone(),
+            timestamp,
+        });
+        self.state = LockState::Unlocked;
+        true
+    }
+
+    pub fn set_jammed(&mut self, jammed: bool) {
+        self.state = match (jammed, self.state) {
+            (true, _) => LockState::Jammed,
+            (false, LockState::Jammed) => LockState::Locked,
+            (false, state) => state,
+        };
+    }
+
+    pub fn lock_state(&self) -> LockState {
+        self.state
+    }
+
+    pub fn audit(&self) -> &[AuditEntry] {
+        &self.audit
+    }
+
+    /// Appends an entry to the audit trail, e.g. one restored from a saved house.
+    pub fn push_audit(&mut self, user: &str, timestamp: u64) {
+        self.audit.push(AuditEntry {
+            user: user.to_string(),
+            timestamp,
+        });
+    }
+}
+
+impl Device for DoorLock {
+    fn new(_value: f64) -> Self {
+        Self {
+            state: LockState::Unlocked,
+            codes: BTreeMap::new(),
+            audit: Vec::new(),
+        }
+    }
+
+    fn get_value(&self) -> f64 {
+        match self.state {
+            LockState::Locked => 1.0,
+            _ => 0.0,
+        }
+    }
+
+    fn get_name(&self) -> String {
+        String::from("DoorLock")
+    }
+
+    fn get_state(&self) -> String {
+        match self.state {
+            LockState::Locked => "LOCKED",
+            LockState::Unlocked => "UNLOCKED",
+            LockState::Jammed => "JAMMED",
+        }
+        .to_string()
+    }
+
+    fn on(&mut self) {
+        self.lock();
+    }
+
+    // Unlocking requires a PIN, so a plain `off` leaves the bolt as it is.
+    fn off(&mut self) {}
+
+    fn get_context(&self) -> DeviceContext {
+        DeviceContext {
+            device_type: DeviceType::DoorLock,
+            value: self.get_value(),
+            is_on: self.state == LockState::Locked,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_door_lock_unlock_with_pin() {
+        let mut lock = DoorLock::new(0.0);
+        assert!(lock.add_code("alice", "1234"));
+        lock.on();
+        assert_eq!(lock.get_state(), "LOCKED".to_string());
+
+        assert!(!lock.unlock("0000", 100));
+        assert_eq!(lock.lock_state(), LockState::Locked);
+
+        assert!(lock.unlock("1234", 100));
+        assert_eq!(lock.get_state(), "UNLOCKED".to_string());
+        assert_eq!(lock.audit().len(), 1);
+        assert_eq!(lock.audit()[0].user, "alice");
+        assert_eq!(lock.audit()[0].timestamp, 100);
+    }
+
+    #[test]
+    fn test_door_lock_duplicate_code() {
+        let mut lock = DoorLock::new(0.0);
+        assert!(lock.add_code("alice", "1234"));
+        assert!(!lock.add_code("bob", "1234"));
+        assert!(lock.remove_code("1234"));
+        assert!(!lock.remove_code("1234"));
+    }
+
+    #[test]
+    fn test_door_lock_jammed() {
+        let mut lock = DoorLock::new(0.0);
+        lock.add_code("alice", "1234");
+        lock.set_jammed(true);
+        assert_eq!(lock.get_state(), "JAMMED".to_string());
+        assert!(!lock.unlock("1234", 100));
+        assert!(!lock.lock());
+
+        lock.set_jammed(false);
+        assert_eq!(lock.lock_state(), LockState::Locked);
+    }
+}
@@ -1,12 +1,19 @@
+pub mod blind;
 pub mod device;
+pub mod door_lock;
 pub mod power_socket;
+pub mod smoke_detector;
 pub mod thermometer;
 
+use crate::blind::Blind;
 use crate::device::Device;
+use crate::door_lock::{DoorLock, LockState};
 use crate::power_socket::PowerSocket;
+use crate::smoke_detector::SmokeDetector;
 use crate::thermometer::Thermometer;
+use std::any::Any;
 use std::collections::HashMap;
-use std::ffi::c_uint;
+use std::ffi::{CStr, c_uint};
 use std::os::raw::c_char;
 use std::sync::RwLock;
 
@@ -22,6 +29,9 @@ lazy_static! {
 pub enum DeviceType {
     PowerSocket,
     Thermometer,
+    DoorLock,
+    Blind,
+    SmokeDetector,
 }
 
 #[repr(C)]
@@ -36,6 +46,9 @@ pub extern "C" fn new_device(device_type: DeviceType, value: f64) -> c_uint {
     let device: Box<dyn Device> = match device_type {
         DeviceType::PowerSocket => Box::new(PowerSocket::new(value)),
         DeviceType::Thermometer => Box::new(Thermometer::new(value)),
+        DeviceType::DoorLock => Box::new(DoorLock::new(value)),
+        DeviceType::Blind => Box::new(Blind::new(value)),
+        DeviceType::SmokeDetector => Box::new(SmokeDetector::new(value)),
     };
 
     match DEVICE_REGISTRY.write() {
@@ -68,12 +81,40 @@ pub extern "C" fn device_off(id: c_uint) -> bool {
     false
 }
 
+#[unsafe(no_mangle)]
+pub extern "C" fn device_set_value(id: c_uint, value: f64) -> bool {
+    let mut registry = DEVICE_REGISTRY.write().unwrap();
+    registry
+        .get_mut(&id)
+        .map(|d| d.set_value(value))
+        .unwrap_or(false)
+}
+
 #[unsafe(no_mangle)]
 pub extern "C" fn device_get_value(id: c_uint) -> f64 {
     let registry = DEVICE_REGISTRY.read().unwrap();
     registry.get(&id).map(|d| d.get_value()).unwrap_or(0.0)
 }
 
+#[unsafe(no_mangle)]
+pub extern "C" fn device_get_energy(id: c_uint) -> f64 {
+    let registry = DEVICE_REGISTRY.read().unwrap();
+    registry.get(&id).map(|d| d.get_energy()).unwrap_or(0.0)
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn device_get_context(id: c_uint) -> DeviceContext {
+    let registry = DEVICE_REGISTRY.read().unwrap();
+    registry
+        .get(&id)
+        .map(|d| d.get_context())
+        .unwrap_or(DeviceContext {
+            device_type: DeviceType::PowerSocket,
+            value: 0.0,
+            is_on: false,
+        })
+}
+
 #[unsafe(no_mangle)]
 pub extern "C" fn device_get_name(id: c_uint, buffer: *mut c_char, len: usize) -> usize {
     let registry = DEVICE_REGISTRY.read().unwrap();
@@ -94,6 +135,187 @@ pub extern "C" fn device_state(id: c_uint, buffer: *mut c_char, len: usize) -> u
     copy_str_to_c(state, buffer, len)
 }
 
+#[unsafe(no_mangle)]
+pub extern "C" fn devices_tick(seconds: f64) {
+    let mut registry = DEVICE_REGISTRY.write().unwrap();
+    for device in registry.values_mut() {
+        device.tick(seconds);
+    }
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn lock_add_code(id: c_uint, user: *const c_char, pin: *const c_char) -> bool {
+    let (Some(user), Some(pin)) = (str_from_c(user), str_from_c(pin)) else {
+        return false;
+    };
+    with_device(id, |lock: &mut DoorLock| lock.add_code(&user, &pin)).unwrap_or(false)
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn lock_remove_code(id: c_uint, pin: *const c_char) -> bool {
+    let Some(pin) = str_from_c(pin) else {
+        return false;
+    };
+    with_device(id, |lock: &mut DoorLock| lock.remove_code(&pin)).unwrap_or(false)
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn lock_code_count(id: c_uint) -> usize {
+    with_device(id, |lock: &mut DoorLock| lock.codes().len()).unwrap_or(0)
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn lock_code_user(
+    id: c_uint,
+    index: usize,
+    buffer: *mut c_char,
+    len: usize,
+) -> usize {
+    let user = with_device(id, |lock: &mut DoorLock| {
+        lock.codes().get(index).map(|(user, _)| user.to_string())
+    })
+    .flatten()
+    .unwrap_or("Unknown".to_string());
+    copy_str_to_c(user, buffer, len)
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn lock_code_pin(
+    id: c_uint,
+    index: usize,
+    buffer: *mut c_char,
+    len: usize,
+) -> usize {
+    let pin = with_device(id, |lock: &mut DoorLock| {
+        lock.codes().get(index).map(|(_, pin)| pin.to_string())
+    })
+    .flatten()
+    .unwrap_or_default();
+    copy_str_to_c(pin, buffer, len)
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn lock_lock(id: c_uint) -> bool {
+    with_device(id, |lock: &mut DoorLock| lock.lock()).unwrap_or(false)
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn lock_unlock(id: c_uint, pin: *const c_char, timestamp: u64) -> bool {
+    let Some(pin) = str_from_c(pin) else {
+        return false;
+    };
+    with_device(id, |lock: &mut DoorLock| lock.unlock(&pin, timestamp)).unwrap_or(false)
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn lock_set_jammed(id: c_uint, jammed: bool) -> bool {
+    with_device(id, |lock: &mut DoorLock| lock.set_jammed(jammed)).is_some()
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn lock_is_jammed(id: c_uint) -> bool {
+    with_device(id, |lock: &mut DoorLock| {
+        lock.lock_state() == LockState::Jammed
+    })
+    .unwrap_or(false)
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn lock_push_audit(id: c_uint, user: *const c_char, timestamp: u64) -> bool {
+    let Some(user) = str_from_c(user) else {
+        return false;
+    };
+    with_device(id, |lock: &mut DoorLock| lock.push_audit(&user, timestamp)).is_some()
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn lock_audit_len(id: c_uint) -> usize {
+    with_device(id, |lock: &mut DoorLock| lock.audit().len()).unwrap_or(0)
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn lock_audit_user(
+    id: c_uint,
+    index: usize,
+    buffer: *mut c_char,
+    len: usize,
+) -> usize {
+    let user = with_device(id, |lock: &mut DoorLock| {
+        lock.audit().get(index).map(|e| e.user.clone())
+    })
+    .flatten()
+    .unwrap_or("Unknown".to_string());
+    copy_str_to_c(user, buffer, len)
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn lock_audit_time(id: c_uint, index: usize) -> u64 {
+    with_device(id, |lock: &mut DoorLock| {
+        lock.audit().get(index).map(|e| e.timestamp)
+    })
+    .flatten()
+    .unwrap_or(0)
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn blind_set_target(id: c_uint, position: f64) -> bool {
+    with_device(id, |blind: &mut Blind| blind.set_target(position)).unwrap_or(false)
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn blind_get_target(id: c_uint) -> f64 {
+    with_device(id, |blind: &mut Blind| blind.get_target()).unwrap_or(0.0)
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn blind_stop(id: c_uint) -> bool {
+    with_device(id, |blind: &mut Blind| blind.stop()).is_some()
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn blind_settle(id: c_uint) -> bool {
+    with_device(id, |blind: &mut Blind| blind.settle()).is_some()
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn blind_get_speed(id: c_uint) -> f64 {
+    with_device(id, |blind: &mut Blind| blind.get_speed()).unwrap_or(0.0)
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn blind_set_speed(id: c_uint, speed: f64) -> bool {
+    with_device(id, |blind: &mut Blind| blind.set_speed(speed)).unwrap_or(false)
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn detector_trigger(id: c_uint) -> bool {
+    with_device(id, |detector: &mut SmokeDetector| detector.trigger()).unwrap_or(false)
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn detector_clear(id: c_uint) -> bool {
+    with_device(id, |detector: &mut SmokeDetector| detector.clear()).is_some()
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn detector_is_alarm(id: c_uint) -> bool {
+    with_device(id, |detector: &mut SmokeDetector| detector.is_alarm()).unwrap_or(false)
+}
+
+fn with_device<T: Device, R>(id: c_uint, f: impl FnOnce(&mut T) -> R) -> Option<R> {
+    let mut registry = DEVICE_REGISTRY.write().unwrap();
+    let device: &mut dyn Any = registry.get_mut(&id)?.as_mut();
+    device.downcast_mut::<T>().map(f)
+}
+
+fn str_from_c(s: *const c_char) -> Option<String> {
+    if s.is_null() {
+        return None;
+    }
+    let s = unsafe { CStr::from_ptr(s) };
+    s.to_str().ok().map(str::to_string)
+}
+
 fn copy_str_to_c(s: String, buffer: *mut c_char, len: usize) -> usize {
     let bytes = s.as_bytes();
     let n = (len.saturating_sub(1)).min(bytes.len());
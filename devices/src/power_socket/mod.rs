@@ -1,8 +1,10 @@
 use crate::device::Device;
+use crate::{DeviceContext, DeviceType};
 
 pub struct PowerSocket {
     power: f64,
     state: String,
+    energy: f64,
 }
 
 impl Default for PowerSocket {
@@ -10,6 +12,7 @@ impl Default for PowerSocket {
         Self {
             power: 0.0,
             state: "OFF".to_string(),
+            energy: 0.0,
         }
     }
 }
@@ -19,6 +22,7 @@ impl Device for PowerSocket {
         Self {
             power: w,
             state: "OFF".to_string(),
+            energy: 0.0,
         }
     }
 
@@ -43,6 +47,31 @@ impl Device for PowerSocket {
     fn off(&mut self) {
         self.state = "OFF".to_string();
     }
+
+    /// Energy consumed so far, in watt-hours of simulated time.
+    fn get_energy(&self) -> f64 {
+        self.energy
+    }
+
+    fn tick(&mut self, seconds: f64) {
+        self.energy += self.get_value() * seconds.max(0.0) / 3600.0;
+    }
+
+    fn set_value(&mut self, w: f64) -> bool {
+        if !w.is_finite() || w < 0.0 {
+            return false;
+        }
+        self.power = w;
+        true
+    }
+
+    fn get_context(&self) -> DeviceContext {
+        DeviceContext {
+            device_type: DeviceType::PowerSocket,
+            value: self.power,
+            is_on: self.state == "ON",
+        }
+    }
 }
 
 #[cfg(test)]
@@ -68,4 +97,24 @@ mod tests {
         assert_eq!(socket.get_state(), "OFF".to_string());
         assert_eq!(socket.get_value(), 0.0);
     }
+
+    #[test]
+    fn test_power_socket_energy() {
+        let mut socket = PowerSocket::new(100.0);
+        socket.tick(3600.0);
+        assert_eq!(socket.get_energy(), 0.0);
+
+        socket.on();
+        socket.tick(1800.0);
+        assert_eq!(socket.get_energy(), 50.0);
+    }
+
+    #[test]
+    fn test_power_socket_set_value() {
+        let mut socket = PowerSocket::new(60.0);
+        socket.on();
+        assert!(socket.set_value(100.0));
+        assert_eq!(socket.get_value(), 100.0);
+        assert!(!socket.set_value(-1.0));
+    }
 }
@@ -0,0 +1,99 @@
+use crate::device::Device;
+use crate::{DeviceContext, DeviceType};
+
+#[derive(Debug)]
+pub struct SmokeDetector {
+    armed: bool,
+    alarm: bool,
+}
+
+impl SmokeDetector {
+    pub fn trigger(&mut self) -> bool {
+        if !self.armed {
+            return false;
+        }
+        self.alarm = true;
+        true
+    }
+
+    pub fn clear(&mut self) {
+        self.alarm = false;
+    }
+
+    pub fn is_alarm(&self) -> bool {
+        self.alarm
+    }
+}
+
+impl Device for SmokeDetector {
+    fn new(_value: f64) -> Self {
+        Self {
+            armed: true,
+            alarm: false,
+        }
+    }
+
+    fn get_value(&self) -> f64 {
+        match self.alarm {
+            true => 1.0,
+            false => 0.0,
+        }
+    }
+
+    fn get_name(&self) -> String {
+        String::from("SmokeDetector")
+    }
+
+    fn get_state(&self) -> String {
+        match (self.armed, self.alarm) {
+            (true, true) => "ALARM",
+            (true, false) => "ON",
+            (false, _) => "OFF",
+        }
+        .to_string()
+    }
+
+    fn on(&mut self) {
+        self.armed = true;
+    }
+
+    fn off(&mut self) {
+        self.armed = false;
+        self.alarm = false;
+    }
+
+    fn get_context(&self) -> DeviceContext {
+        DeviceContext {
+            device_type: DeviceType::SmokeDetector,
+            value: self.get_value(),
+            is_on: self.armed,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_smoke_detector_trigger_and_clear() {
+        let mut detector = SmokeDetector::new(0.0);
+        assert_eq!(detector.get_state(), "ON".to_string());
+
+        assert!(detector.trigger());
+        assert_eq!(detector.get_state(), "ALARM".to_string());
+        assert_eq!(detector.get_value(), 1.0);
+
+        detector.clear();
+        assert!(!detector.is_alarm());
+        assert_eq!(detector.get_state(), "ON".to_string());
+    }
+
+    #[test]
+    fn test_smoke_detector_disarmed() {
+        let mut detector = SmokeDetector::new(0.0);
+        detector.off();
+        assert!(!detector.trigger());
+        assert_eq!(detector.get_state(), "OFF".to_string());
+    }
+}
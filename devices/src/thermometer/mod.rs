@@ -1,4 +1,5 @@
 use crate::device::Device;
+use crate::{DeviceContext, DeviceType};
 
 #[derive(Debug, Default)]
 pub struct Thermometer {
@@ -36,6 +37,22 @@ impl Device for Thermometer {
     fn off(&mut self) {
         self.state = "OFF".to_string();
     }
+
+    fn set_value(&mut self, t: f64) -> bool {
+        if !t.is_finite() {
+            return false;
+        }
+        self.temperature = t;
+        true
+    }
+
+    fn get_context(&self) -> DeviceContext {
+        DeviceContext {
+            device_type: DeviceType::Thermometer,
+            value: self.temperature,
+            is_on: self.state == "ON",
+        }
+    }
 }
 
 #[cfg(test)]
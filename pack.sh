@@ -1,3 +1,3 @@
 #!/bin/bash
 
-zip -9 -r "$HOME/Downloads/smart_house_$(date +'%d-%m-%y').zip" core/ devices/ Cargo.toml README.md
\ No newline at end of file
+zip -9 -r "$HOME/Downloads/smart_house_$(date +'%d-%m-%y').zip" core/ devices/ Cargo.toml README.md house.toml
\ No newline at end of file